@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::level::Activated;
+use crate::player::{LevelState, PlayerMarker};
+use crate::profile::Profile;
+use crate::results::LevelCompletedEvent;
+use crate::sound::SoundEvent;
+
+pub const MAX_COINS: usize = 3;
+
+//activated means collected in the current run
+#[derive(Component)]
+pub struct Coin {
+    pub index: u8,
+}
+
+fn collect_coins(
+    player_query: Query<Entity, With<PlayerMarker>>,
+    mut coin_query: Query<(Entity, &mut Activated), With<Coin>>,
+    rapier_context: Res<RapierContext>,
+    level_state: Res<LevelState>,
+    mut sound_ev: EventWriter<SoundEvent>,
+) {
+    if !level_state.active || level_state.death_timer.is_some() {
+        return;
+    }
+
+    for player_id in player_query.iter() {
+        for (coin_id, mut activated) in coin_query.iter_mut() {
+            if !activated.0 && rapier_context.intersection_pair(player_id, coin_id) == Some(true) {
+                activated.0 = true;
+                sound_ev.send(SoundEvent::Coin);
+            }
+        }
+    }
+}
+
+fn update_coin_visibility(mut coin_query: Query<(&Activated, &mut Visibility), (With<Coin>, Changed<Activated>)>) {
+    for (activated, mut visibility) in coin_query.iter_mut() {
+        visibility.is_visible = !activated.0;
+    }
+}
+
+//coins only count once the level is completed in the same run, never in practice or with cheats
+fn record_coins(
+    mut level_completed_ev: EventReader<LevelCompletedEvent>,
+    coin_query: Query<(&Coin, &Activated)>,
+    level_state: Res<LevelState>,
+    mut profile: ResMut<Profile>,
+) {
+    if level_completed_ev.iter().last().is_none() || level_state.practice || level_state.cheated {
+        return;
+    }
+
+    for (coin, activated) in coin_query.iter() {
+        if activated.0 {
+            profile.collect_coin(&level_state.level_key, coin.index);
+        }
+    }
+}
+
+pub struct CoinPlugin;
+impl Plugin for CoinPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(collect_coins)
+            .add_system(update_coin_visibility.after(collect_coins))
+            .add_system(record_coins);
+    }
+}
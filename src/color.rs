@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use bevy::prelude::*;
+
+use crate::player::RespawnPlayerEvent;
+use crate::BG_COLOR;
+
+//1 to 999 are free for the level's creator
+pub const MAX_USER_CHANNEL: u16 = 999;
+pub const BACKGROUND_CHANNEL: u16 = 1000;
+pub const GROUND_CHANNEL: u16 = 1001;
+pub const LINE_CHANNEL: u16 = 1002;
+
+//the sprite's color follows the channel, its alpha is left alone for alpha triggers
+#[derive(Component)]
+pub struct ColorChannel(pub u16);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ChannelColor {
+    pub channel: u16,
+    pub color: [f32; 3],
+}
+
+pub fn channel_name(channel: u16) -> String {
+    match channel {
+        BACKGROUND_CHANNEL => "Background".to_string(),
+        GROUND_CHANNEL => "Ground".to_string(),
+        LINE_CHANNEL => "Line".to_string(),
+        _ => format!("Channel {}", channel),
+    }
+}
+
+fn default_color(channel: u16) -> [f32; 3] {
+    match channel {
+        BACKGROUND_CHANNEL => [BG_COLOR.r(), BG_COLOR.g(), BG_COLOR.b()],
+        _ => [1.0, 1.0, 1.0],
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ColorChannels {
+    base: HashMap<u16, [f32; 3]>, //colors the level starts with
+    current: HashMap<u16, [f32; 3]>,
+}
+
+impl ColorChannels {
+    pub fn load(&mut self, colors: &[ChannelColor]) {
+        self.base = colors.iter().map(|color| (color.channel, color.color)).collect();
+        self.current = self.base.clone();
+    }
+
+    //what gets written into the level file
+    pub fn base_colors(&self) -> Vec<ChannelColor> {
+        let mut colors: Vec<ChannelColor> = self
+            .base
+            .iter()
+            .map(|(channel, color)| ChannelColor {
+                channel: *channel,
+                color: *color,
+            })
+            .collect();
+        colors.sort_by_key(|color| color.channel);
+        colors
+    }
+
+    pub fn color(&self, channel: u16) -> [f32; 3] {
+        self.current.get(&channel).copied().unwrap_or_else(|| default_color(channel))
+    }
+
+    pub fn set_base(&mut self, channel: u16, color: [f32; 3]) {
+        self.base.insert(channel, color);
+        self.current.insert(channel, color);
+    }
+
+    //moves the channel the given share of the way towards the target
+    pub fn blend(&mut self, channel: u16, target: [f32; 3], share: f32) {
+        let mut color = self.color(channel);
+        for i in 0..3 {
+            color[i] += (target[i] - color[i]) * share;
+        }
+        self.current.insert(channel, color);
+    }
+
+    fn reset(&mut self) {
+        self.current = self.base.clone();
+    }
+}
+
+fn apply_color_channels(
+    channels: Res<ColorChannels>,
+    mut clear_color: ResMut<ClearColor>,
+    mut sprite_query: Query<(ChangeTrackers<ColorChannel>, &ColorChannel, &mut Sprite)>,
+) {
+    if channels.is_changed() {
+        let [r, g, b] = channels.color(BACKGROUND_CHANNEL);
+        clear_color.0 = Color::rgb(r, g, b);
+    }
+
+    for (tracker, channel, mut sprite) in sprite_query.iter_mut() {
+        if channels.is_changed() || tracker.is_changed() {
+            let [r, g, b] = channels.color(channel.0);
+            let alpha = sprite.color.a();
+            sprite.color = Color::rgba(r, g, b, alpha);
+        }
+    }
+}
+
+fn reset_color_channels(mut respawn_player_ev: EventReader<RespawnPlayerEvent>, mut channels: ResMut<ColorChannels>) {
+    if respawn_player_ev.iter().last().is_some() {
+        channels.reset();
+    }
+}
+
+pub struct ColorPlugin;
+impl Plugin for ColorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColorChannels>()
+            .add_system(reset_color_channels.before(apply_color_channels))
+            .add_system(apply_color_channels);
+    }
+}
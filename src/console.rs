@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_rapier2d::prelude::*;
+
+use crate::color::ColorChannels;
+use crate::editor::{edited_level, has_unsaved_changes, EditorState};
+use crate::input::{ActionState, InputAction};
+use crate::level::{lint_level, try_load_level, LevelObject};
+use crate::player::{LevelState, PlayerMarker, ReloadLevelEvent};
+use crate::{GameState, GameStateVariant};
+
+const MAX_LINES: usize = 200;
+
+//sent when a registered command is entered, the module that registered it handles it
+pub struct ConsoleCommandEvent {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+//a line printed in the console, usually the answer to a command
+pub struct ConsoleReply(pub String);
+
+impl ConsoleReply {
+    pub fn new(line: impl Into<String>) -> Self {
+        ConsoleReply(line.into())
+    }
+}
+
+//commands by name with their help line
+#[derive(Resource, Default)]
+pub struct ConsoleCommands {
+    commands: HashMap<&'static str, &'static str>,
+}
+
+pub trait ConsoleAppExt {
+    //makes the console pass the command on as a ConsoleCommandEvent
+    fn add_console_command(&mut self, name: &'static str, help: &'static str) -> &mut Self;
+}
+
+impl ConsoleAppExt for App {
+    fn add_console_command(&mut self, name: &'static str, help: &'static str) -> &mut Self {
+        self.init_resource::<ConsoleCommands>();
+        self.world.resource_mut::<ConsoleCommands>().commands.insert(name, help);
+        self
+    }
+}
+
+#[derive(Resource, Default)]
+struct ConsoleState {
+    open: bool,
+    focus: bool, //the text field takes the keyboard when the console opens
+    input: String,
+    lines: Vec<String>,
+}
+
+impl ConsoleState {
+    fn print(&mut self, line: String) {
+        self.lines.push(line);
+        if self.lines.len() > MAX_LINES {
+            self.lines.remove(0);
+        }
+    }
+}
+
+fn toggle_console(actions: Res<ActionState>, game_state: Res<GameState>, mut console: ResMut<ConsoleState>) {
+    if game_state.variant != GameStateVariant::Menu && actions.just_pressed(InputAction::ToggleConsole) {
+        console.open = !console.open;
+        console.focus = console.open;
+    }
+}
+
+fn run_command(
+    line: &str,
+    console: &mut ConsoleState,
+    commands: &ConsoleCommands,
+    command_ev: &mut EventWriter<ConsoleCommandEvent>,
+) {
+    let mut words = line.split_whitespace().map(|word| word.to_string());
+    let name = match words.next() {
+        Some(name) => name,
+        None => return,
+    };
+    console.print(format!("> {}", line));
+
+    match name.as_str() {
+        "help" => {
+            let mut names: Vec<(&&str, &&str)> = commands.commands.iter().collect();
+            names.sort();
+            console.print("help - lists the commands".to_string());
+            console.print("clear - empties the console".to_string());
+            for (name, help) in names {
+                console.print(format!("{} - {}", name, help));
+            }
+        }
+        "clear" => console.lines.clear(),
+        _ if commands.commands.contains_key(name.as_str()) => command_ev.send(ConsoleCommandEvent {
+            name,
+            args: words.collect(),
+        }),
+        _ => console.print(format!("Unknown command '{}', try 'help'", name)),
+    }
+}
+
+fn console_panel(
+    mut egui_context: ResMut<EguiContext>,
+    mut console: ResMut<ConsoleState>,
+    mut reply_ev: EventReader<ConsoleReply>,
+    mut command_ev: EventWriter<ConsoleCommandEvent>,
+    commands: Res<ConsoleCommands>,
+) {
+    for reply in reply_ev.iter() {
+        console.print(reply.0.clone());
+    }
+    if !console.open {
+        return;
+    }
+
+    let mut open = true;
+    let mut entered = None;
+    egui::Window::new("Console")
+        .open(&mut open)
+        .default_width(500.0)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in console.lines.iter() {
+                        ui.monospace(line);
+                    }
+                });
+
+            let response = ui.add(egui::TextEdit::singleline(&mut console.input).desired_width(f32::INFINITY));
+            if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                entered = Some(std::mem::take(&mut console.input));
+                console.focus = true;
+            }
+            //the pause binding is ignored while typing, so escape closes the console instead
+            if response.lost_focus() && ui.input().key_pressed(egui::Key::Escape) {
+                console.open = false;
+            }
+            if console.focus {
+                response.request_focus();
+                console.focus = false;
+            }
+        });
+
+    if !open {
+        console.open = false;
+    }
+    if let Some(line) = entered {
+        run_command(&line, &mut console, &commands, &mut command_ev);
+    }
+}
+
+fn count_entities(
+    mut command_ev: EventReader<ConsoleCommandEvent>,
+    mut reply_ev: EventWriter<ConsoleReply>,
+    entity_query: Query<Entity>,
+    object_query: Query<&LevelObject>,
+    collider_query: Query<(), With<Collider>>,
+    player_query: Query<(), With<PlayerMarker>>,
+) {
+    for _ in command_ev.iter().filter(|command| command.name == "entities") {
+        reply_ev.send(ConsoleReply(format!(
+            "{} entities: {} level objects ({} triggers), {} colliders, {} players",
+            entity_query.iter().count(),
+            object_query.iter().count(),
+            object_query.iter().filter(|object| object.info.marker_type == 11).count(),
+            collider_query.iter().count(),
+            player_query.iter().count(),
+        )));
+    }
+}
+
+//closes whatever is open and opens the new level the same way
+fn load_command(
+    mut command_ev: EventReader<ConsoleCommandEvent>,
+    mut reply_ev: EventWriter<ConsoleReply>,
+    mut reload_level_ev: EventWriter<ReloadLevelEvent>,
+    mut game_state: ResMut<GameState>,
+    level_state: Res<LevelState>,
+    editor_state: Res<EditorState>,
+    object_query: Query<&LevelObject>,
+    color_channels: Res<ColorChannels>,
+) {
+    for command in command_ev.iter().filter(|command| command.name == "load") {
+        let path = match command.args.first() {
+            Some(path) => path.clone(),
+            None => {
+                reply_ev.send(ConsoleReply::new("Usage: load <path>"));
+                continue;
+            }
+        };
+        if try_load_level(&path).is_none() {
+            reply_ev.send(ConsoleReply(format!("Couldn't read a level from {}", path)));
+            continue;
+        }
+
+        if !level_state.active && !editor_state.active {
+            reply_ev.send(ConsoleReply::new("Open a level or the editor first"));
+            continue;
+        }
+        if has_unsaved_changes(&editor_state, &object_query, &color_channels) {
+            reply_ev.send(ConsoleReply::new("The edited level has unsaved changes, save it first"));
+            continue;
+        }
+
+        game_state.level_path = path.clone();
+        reload_level_ev.send_default();
+        reply_ev.send(ConsoleReply(format!("Loading {}", path)));
+    }
+}
+
+//checks a level without playing it, the one in the editor as it is being edited
+fn lint_command(
+    mut command_ev: EventReader<ConsoleCommandEvent>,
+    mut reply_ev: EventWriter<ConsoleReply>,
+    game_state: Res<GameState>,
+    editor_state: Res<EditorState>,
+    object_query: Query<&LevelObject>,
+    color_channels: Res<ColorChannels>,
+) {
+    for command in command_ev.iter().filter(|command| command.name == "lint") {
+        let path = command.args.first().unwrap_or(&game_state.level_path).clone();
+        let level = if editor_state.active && command.args.is_empty() {
+            edited_level(&editor_state, &object_query, &color_channels)
+        } else {
+            match try_load_level(&path) {
+                Some(level) => level,
+                None => {
+                    reply_ev.send(ConsoleReply(format!("Couldn't read a level from {}", path)));
+                    continue;
+                }
+            }
+        };
+
+        let problems = lint_level(&level);
+        if problems.is_empty() {
+            reply_ev.send(ConsoleReply(format!("{}: no problems found", path)));
+        } else {
+            reply_ev.send(ConsoleReply(format!("{}: {} problems", path, problems.len())));
+            for problem in problems {
+                reply_ev.send(ConsoleReply(format!("  {}", problem)));
+            }
+        }
+    }
+}
+
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleState>()
+            .add_event::<ConsoleCommandEvent>()
+            .add_event::<ConsoleReply>()
+            .add_console_command("entities", "counts the entities in the world")
+            .add_console_command("load", "load <path> - opens a level file in the current mode")
+            .add_console_command(
+                "lint",
+                "lint [path] - checks the given level file, or the current level as it is being edited",
+            )
+            .add_system(toggle_console)
+            .add_system(console_panel.after(toggle_console))
+            .add_system(count_entities)
+            .add_system(load_command)
+            .add_system(lint_command);
+    }
+}
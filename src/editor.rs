@@ -1,11 +1,24 @@
-use serde::{Serialize};
-
-use bevy::{input::mouse::MouseScrollUnit, input::mouse::MouseWheel, prelude::*};
+use bevy::{audio::AudioSink, input::mouse::MouseScrollUnit, input::mouse::MouseWheel, prelude::*};
 use bevy_prototype_debug_lines::{DebugLines, DebugLinesPlugin};
-use bevy_rapier2d::prelude::*;
-//use bevy_egui::{egui, EguiContext, EguiPlugin};
+use bevy_egui::{egui, EguiContext};
 
-use crate::{GameAssets, GameState, GameStateVariant, GroundMarker};
+use crate::color::{
+    channel_name, ColorChannel, ColorChannels, BACKGROUND_CHANNEL, GROUND_CHANNEL, LINE_CHANNEL, MAX_USER_CHANNEL,
+};
+use crate::console::{ConsoleAppExt, ConsoleCommandEvent, ConsoleReply};
+use crate::input::{ActionState, InputAction};
+use crate::level::{
+    base_color, blocks_end, load_level, portal_color, spawn_block, speed_sections, time_at_x, x_at_time, BlockInfo,
+    Difficulty, LevelFile, LevelHeader, LevelObject,
+};
+use crate::coin::MAX_COINS;
+use crate::music::{LevelMusic, MusicPlayer};
+use crate::pad::PadColor;
+use crate::player::{LevelState, PlayerSize, ReloadLevelEvent, SpeedTier, STARTING_PLAYER_POSTION};
+use crate::portal::SpeedPortal;
+use crate::sound::SoundEvent;
+use crate::trigger::{Easing, TriggerInfo, TriggerKind};
+use crate::{game_camera, GameAssets, GameState, GameStateVariant, VIEW_HEIGHT};
 
 #[derive(Component)]
 struct EditorCameraMarker;
@@ -17,23 +30,99 @@ struct BlockButton {
     id: u8,
 }
 
+#[derive(Default)]
+struct SaveLevelEvent;
+
 const CAMERA_ZOOM_SPEED: f32 = 10.0;
 const BLOCK_SIZE: f32 = 64.0;
+const PALETTE_SIZE: u8 = 33; //3 blocks, 5 speed portals, size, mirror and dual portal pairs, 4 pads, 4 orbs, the level end, a coin and 9 triggers
+const GUIDELINE_SECONDS: usize = 30;
+const BEATS_PER_BAR: u32 = 4;
 
 #[derive(Resource)]
 pub struct EditorState {
     pub active: bool,
     pub picked_block_id: u8,
     pub freeze_block_placing: bool,
+    pub header: LevelHeader,
+    pub selected: Option<Entity>,    //object shown in the property panel
+    pub groups_text: String,         //groups of the selected object as typed in the panel
+    pub picked_channel: u16,         //user color channel shown in the colors panel
+    pub status: String,              //the last thing the editor has to tell, shown in the level panel
+    pub saved_contents: Vec<String>, //the level as it was opened or last saved, see level_contents
 }
 
-
-#[derive(Serialize)]
-pub struct BlockInfo {
-    id: u8,
-    name: String,
-    marker_type: u8, //0- spike, 1- block, 2- ClippedBlockMarker
-    coords: (i32, i32),
+//turns a palette button id into the object it places
+fn palette_block(id: u8, coords: (i32, i32)) -> BlockInfo {
+    match id {
+        0..=2 => BlockInfo::new(id, &format!("Block{}", id), 1, coords),
+        3..=7 => {
+            let tier = SpeedTier::ALL[(id - 3) as usize];
+            BlockInfo {
+                speed: Some(tier),
+                ..BlockInfo::new(0, &format!("SpeedPortal{:?}", tier), 3, coords)
+            }
+        }
+        8 | 9 => {
+            let size = if id == 8 { PlayerSize::Mini } else { PlayerSize::Normal };
+            BlockInfo {
+                size: Some(size),
+                ..BlockInfo::new(0, &format!("SizePortal{:?}", size), 4, coords)
+            }
+        }
+        10 | 11 => BlockInfo {
+            mirrored: Some(id == 10),
+            ..BlockInfo::new(0, "MirrorPortal", 5, coords)
+        },
+        12 | 13 => BlockInfo {
+            dual: Some(id == 12),
+            ..BlockInfo::new(0, "DualPortal", 6, coords)
+        },
+        14..=17 => {
+            let color = PadColor::ALL[(id - 14) as usize];
+            BlockInfo {
+                pad: Some(color),
+                ..BlockInfo::new(0, &format!("JumpPad{:?}", color), 7, coords)
+            }
+        }
+        18..=21 => {
+            let color = PadColor::ALL[(id - 18) as usize];
+            BlockInfo {
+                pad: Some(color),
+                ..BlockInfo::new(0, &format!("JumpOrb{:?}", color), 8, coords)
+            }
+        }
+        22 => BlockInfo::new(0, "LevelEnd", 9, coords),
+        23 => BlockInfo::new(0, "Coin", 10, coords),
+        _ => {
+            let kind = match id {
+                24 => TriggerInfo::default_move().kind,
+                25 => TriggerKind::Rotate {
+                    degrees: 90.0,
+                    center_group: 0,
+                },
+                26 => TriggerKind::Alpha { opacity: 0.0 },
+                27 => TriggerKind::Toggle { enabled: false },
+                28 => TriggerKind::Color {
+                    channel: BACKGROUND_CHANNEL,
+                    color: [0.0, 0.0, 0.0],
+                },
+                29 => TriggerKind::CameraOffset { offset: (0.0, 0.0) },
+                30 => TriggerKind::CameraZoom { zoom: 1.0 },
+                31 => TriggerKind::CameraStatic {
+                    position: Some((coords.0 as f32, coords.1 as f32)),
+                },
+                _ => TriggerKind::CameraSection {
+                    bounds: Some((coords.1 as f32 - 320.0, coords.1 as f32 + 320.0)),
+                },
+            };
+            BlockInfo {
+                name: format!("{}Trigger", kind.name()),
+                trigger: Some(TriggerInfo::new(kind)),
+                ..BlockInfo::new(0, "", 11, coords)
+            }
+        }
+    }
 }
 
 //used once when transitioning from level to editor
@@ -41,24 +130,28 @@ fn editor_open(
     mut commands: Commands,
     game_state: Res<GameState>,
     mut editor_state: ResMut<EditorState>,
+    mut color_channels: ResMut<ColorChannels>,
     game_assets: Res<GameAssets>,
 ) {
     if game_state.variant == GameStateVariant::Editor && !editor_state.active {
         commands.spawn((
-            Camera2dBundle {
-                transform: Transform {
-                    translation: Vec3::new(0.0, 0.0, 0.0),
-                    ..default()
-                },
-                ..default()
-            },
+            game_camera(Vec3::new(0.0, 0.0, 0.0)),
             EditorCameraMarker,
             Name::new("EditorCamera"),
         ));
 
+        let level = load_level(&game_state.level_path);
+        for block_info in level.blocks.iter() {
+            spawn_block(&mut commands, &game_assets, block_info);
+        }
+        editor_state.saved_contents = level_contents(&level);
+        editor_state.header = level.header;
+        color_channels.load(&level.colors);
+
         spawn_button(commands, game_assets, game_state);
         editor_state.active = true;
         editor_state.freeze_block_placing = false;
+        editor_state.status.clear();
     }
 }
 
@@ -72,15 +165,26 @@ fn editor_close(
             Without<EditorCameraMarker>,
         ),
     >,
+    object_query: Query<Entity, With<LevelObject>>,
     game_state: Res<GameState>,
     mut editor_state: ResMut<EditorState>,
+    mut music_player: ResMut<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut reload_level_ev: EventReader<ReloadLevelEvent>,
 ) {
-    if game_state.variant == GameStateVariant::Level && editor_state.active {
+    //editor_open runs first, so a reloaded level opens again on the next frame
+    let reload = reload_level_ev.iter().last().is_some();
+    if (game_state.variant != GameStateVariant::Editor || reload) && editor_state.active {
+        music_player.stop(&sinks);
+        for object_entity in object_query.iter() {
+            commands.entity(object_entity).despawn();
+        }
         for entity in camera_query.iter() {
             for node_entity in node_query.iter() {
                 commands.entity(entity).despawn();
                 commands.entity(node_entity).despawn_recursive();
                 editor_state.active = false;
+                editor_state.selected = None;
             }
         }
     }
@@ -109,7 +213,7 @@ fn camera_zoom(
 }
 
 fn camera_movement(
-    keyboard: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
     mut camera_query: Query<&mut Transform, With<EditorCameraMarker>>,
     time: Res<Time>,
     game_state: Res<GameState>,
@@ -122,16 +226,16 @@ fn camera_movement(
 
             let speed = 1000.0;
 
-            if keyboard.pressed(KeyCode::A) {
+            if actions.pressed(InputAction::EditorLeft) {
                 transform.translation += left * time.delta_seconds() * speed;
             }
-            if keyboard.pressed(KeyCode::D) {
+            if actions.pressed(InputAction::EditorRight) {
                 transform.translation -= left * time.delta_seconds() * speed;
             }
-            if keyboard.pressed(KeyCode::S) {
+            if actions.pressed(InputAction::EditorDown) {
                 transform.translation -= Vec3::Y * time.delta_seconds() * speed;
             }
-            if keyboard.pressed(KeyCode::W) {
+            if actions.pressed(InputAction::EditorUp) {
                 transform.translation += Vec3::Y * time.delta_seconds() * speed;
             }
         }
@@ -168,27 +272,59 @@ fn draw_editor_lines(
     }
 }
 
+//worked out from the window itself, the camera's viewport only catches up with a resize at the end of the frame
+fn cursor_world_position(window: &Window, camera_transform: &Transform) -> Option<Vec2> {
+    let cursor = window.cursor_position()?;
+    let window_size = Vec2::new(window.width(), window.height());
+    if window_size.y <= 0.0 {
+        return None;
+    }
+
+    let view_size = Vec2::new(VIEW_HEIGHT * window_size.x / window_size.y, VIEW_HEIGHT);
+    let offset = (cursor / window_size - Vec2::splat(0.5)) * view_size;
+    Some(camera_transform.transform_point(offset.extend(0.0)).truncate())
+}
+
+//every coin gets the first index that isn't taken yet, false when all of them are
+fn number_coin(block_info: &mut BlockInfo, object_query: &Query<&LevelObject>) -> bool {
+    if block_info.marker_type != 10 {
+        return true;
+    }
+
+    let taken: Vec<u8> = object_query
+        .iter()
+        .filter_map(|object| object.info.coin)
+        .collect();
+    match (0..MAX_COINS as u8).find(|index| !taken.contains(index)) {
+        Some(index) => {
+            block_info.coin = Some(index);
+            block_info.name = format!("Coin{}", index);
+            true
+        }
+        None => false,
+    }
+}
+
 fn place_blocks(
     windows: Res<Windows>,
-    buttons: Res<Input<MouseButton>>,
+    actions: Res<ActionState>,
     game_assets: Res<GameAssets>,
     mut commands: Commands,
-    editor_state: Res<EditorState>,
-    camera_query: Query<(&Camera, &GlobalTransform), With<EditorCameraMarker>>,
+    mut editor_state: ResMut<EditorState>,
+    mut egui_context: ResMut<EguiContext>,
+    camera_query: Query<&Transform, With<EditorCameraMarker>>,
+    object_query: Query<&LevelObject>,
+    mut sound_ev: EventWriter<SoundEvent>,
 ) {
     let window = windows.get_primary().unwrap();
 
-    if editor_state.freeze_block_placing == true {
+    if editor_state.freeze_block_placing == true || egui_context.ctx_mut().is_pointer_over_area() {
         return;
     }
 
-    if buttons.just_pressed(MouseButton::Left) {
-        for (camera, camera_transform) in camera_query.iter() {
-            if let Some(world_position) = window
-                .cursor_position()
-                .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-                .map(|ray| ray.origin.truncate())
-            {
+    if actions.just_pressed(InputAction::EditorPlace) {
+        for camera_transform in camera_query.iter() {
+            if let Some(world_position) = cursor_world_position(window, camera_transform) {
                 let mut pos = Vec3::new(0.0, 0.0, 0.0);
                 let r = world_position.x as i32 % BLOCK_SIZE as i32;
                 if r < (BLOCK_SIZE / 2.0) as i32 {
@@ -204,20 +340,289 @@ fn place_blocks(
                     pos.y = world_position.y + (BLOCK_SIZE - r as f32);
                 }
 
-                commands
-                    .spawn(SpriteBundle {
-                        texture: game_assets.blocks[editor_state.picked_block_id as usize].clone(),
-                        ..default()
-                    })
-                    .insert(Transform {
-                        translation: pos,
-                        scale: Vec3::new(2.0, 2.0, 1.0),
-                        ..default()
+                let mut block_info =
+                    palette_block(editor_state.picked_block_id, (pos.x as i32, pos.y as i32));
+
+                if !number_coin(&mut block_info, &object_query) {
+                    editor_state.status = format!("A level can't have more than {} coins", MAX_COINS);
+                    return;
+                }
+
+                spawn_block(&mut commands, &game_assets, &block_info);
+                sound_ev.send(SoundEvent::EditorPlace);
+            }
+        }
+    }
+}
+
+//right click picks the object under the cursor for the property panel
+fn select_object(
+    windows: Res<Windows>,
+    actions: Res<ActionState>,
+    mut editor_state: ResMut<EditorState>,
+    camera_query: Query<&Transform, With<EditorCameraMarker>>,
+    object_query: Query<(Entity, &Transform, &LevelObject)>,
+) {
+    if !editor_state.active || !actions.just_pressed(InputAction::EditorSelect) {
+        return;
+    }
+
+    let window = windows.get_primary().unwrap();
+    for camera_transform in camera_query.iter() {
+        if let Some(world_position) = cursor_world_position(window, camera_transform) {
+            let nearest = object_query
+                .iter()
+                .map(|(entity, transform, object)| {
+                    (entity, transform.translation.truncate().distance(world_position), object)
+                })
+                .filter(|(_, distance, _)| *distance < BLOCK_SIZE / 2.0)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+            editor_state.selected = nearest.map(|(entity, _, _)| entity);
+            editor_state.groups_text = nearest
+                .map(|(_, _, object)| {
+                    let groups: Vec<String> = object.info.groups.iter().map(|group| group.to_string()).collect();
+                    groups.join(", ")
+                })
+                .unwrap_or_default();
+        }
+    }
+}
+
+fn delete_selected(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    mut editor_state: ResMut<EditorState>,
+    mut sound_ev: EventWriter<SoundEvent>,
+) {
+    if !editor_state.active || !actions.just_pressed(InputAction::EditorDelete) {
+        return;
+    }
+
+    if let Some(selected) = editor_state.selected.take() {
+        commands.entity(selected).despawn_recursive();
+        sound_ev.send(SoundEvent::EditorDelete);
+    }
+}
+
+fn parse_groups(text: &str) -> Vec<u16> {
+    let mut groups: Vec<u16> = text
+        .split(',')
+        .filter_map(|group| group.trim().parse().ok())
+        .filter(|group| *group > 0)
+        .collect();
+    groups.sort();
+    groups.dedup();
+    groups
+}
+
+fn property_panel(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut editor_state: ResMut<EditorState>,
+    mut object_query: Query<(&mut LevelObject, &mut Sprite)>,
+) {
+    if !editor_state.active {
+        return;
+    }
+    let selected = match editor_state.selected {
+        Some(selected) => selected,
+        None => return,
+    };
+    let (mut object, mut sprite) = match object_query.get_mut(selected) {
+        Ok(object) => object,
+        Err(_) => {
+            editor_state.selected = None;
+            return;
+        }
+    };
+
+    egui::Window::new("Properties").show(egui_context.ctx_mut(), |ui| {
+        ui.label(object.info.name.as_str());
+        ui.horizontal(|ui| {
+            ui.label("Groups");
+            if ui.text_edit_singleline(&mut editor_state.groups_text).changed() {
+                object.info.groups = parse_groups(&editor_state.groups_text);
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut channel = object.info.color_channel.unwrap_or(0);
+            ui.label("Color channel");
+            if ui.add(egui::DragValue::new(&mut channel).clamp_range(0..=LINE_CHANNEL)).changed() {
+                //0 means the object keeps its own color
+                if channel == 0 {
+                    object.info.color_channel = None;
+                    commands.entity(selected).remove::<ColorChannel>();
+                    let alpha = sprite.color.a();
+                    sprite.color = base_color(&object.info);
+                    sprite.color.set_a(alpha);
+                } else {
+                    object.info.color_channel = Some(channel);
+                    commands.entity(selected).insert(ColorChannel(channel));
+                }
+            }
+        });
+
+        if let Some(trigger) = object.info.trigger.as_mut() {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Target group");
+                ui.add(egui::DragValue::new(&mut trigger.target_group).clamp_range(1..=9999));
+            });
+            match &mut trigger.kind {
+                TriggerKind::Move { offset } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Offset");
+                        ui.add(egui::DragValue::new(&mut offset.0).prefix("x: "));
+                        ui.add(egui::DragValue::new(&mut offset.1).prefix("y: "));
+                    });
+                }
+                TriggerKind::Rotate { degrees, center_group } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Degrees");
+                        ui.add(egui::DragValue::new(degrees));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Center group");
+                        ui.add(egui::DragValue::new(center_group).clamp_range(0..=9999));
                     })
-                    .insert(RigidBody::Fixed)
-                    .insert(Collider::cuboid(15.0, 15.0))
-                    .insert(GroundMarker);
+                    .response
+                    .on_hover_text("Rotates around the middle of the group's objects, 0 spins each object in place");
+                }
+                TriggerKind::Alpha { opacity } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Opacity");
+                        ui.add(egui::Slider::new(opacity, 0.0..=1.0));
+                    });
+                }
+                TriggerKind::Toggle { enabled } => {
+                    ui.checkbox(enabled, "Enabled");
+                }
+                TriggerKind::Color { channel, color } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Channel");
+                        ui.add(egui::DragValue::new(channel).clamp_range(1..=LINE_CHANNEL));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Color");
+                        ui.color_edit_button_rgb(color);
+                    });
+                }
+                TriggerKind::CameraOffset { offset } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Offset");
+                        ui.add(egui::DragValue::new(&mut offset.0).prefix("x: "));
+                        ui.add(egui::DragValue::new(&mut offset.1).prefix("y: "));
+                    });
+                }
+                TriggerKind::CameraZoom { zoom } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Zoom");
+                        ui.add(egui::DragValue::new(zoom).speed(0.01).clamp_range(0.1..=10.0));
+                    });
+                }
+                TriggerKind::CameraStatic { position } => {
+                    let mut fixed = position.is_some();
+                    ui.checkbox(&mut fixed, "Static");
+                    if fixed != position.is_some() {
+                        *position = if fixed { Some((0.0, 0.0)) } else { None };
+                    }
+                    if let Some(position) = position {
+                        ui.horizontal(|ui| {
+                            ui.label("Position");
+                            ui.add(egui::DragValue::new(&mut position.0).prefix("x: "));
+                            ui.add(egui::DragValue::new(&mut position.1).prefix("y: "));
+                        });
+                    }
+                }
+                TriggerKind::CameraSection { bounds } => {
+                    let mut fixed = bounds.is_some();
+                    ui.checkbox(&mut fixed, "Fixed section");
+                    if fixed != bounds.is_some() {
+                        *bounds = if fixed { Some((-320.0, 320.0)) } else { None };
+                    }
+                    if let Some(bounds) = bounds {
+                        ui.horizontal(|ui| {
+                            ui.label("Floor");
+                            ui.add(egui::DragValue::new(&mut bounds.0));
+                            ui.label("Ceiling");
+                            ui.add(egui::DragValue::new(&mut bounds.1));
+                        });
+                    }
+                }
+            }
+            ui.horizontal(|ui| {
+                ui.label("Duration");
+                ui.add(
+                    egui::DragValue::new(&mut trigger.duration)
+                        .speed(0.05)
+                        .clamp_range(0.0..=60.0)
+                        .suffix("s"),
+                );
+            });
+            egui::ComboBox::from_label("Easing")
+                .selected_text(format!("{:?}", trigger.easing))
+                .show_ui(ui, |ui| {
+                    for easing in Easing::ALL {
+                        ui.selectable_value(&mut trigger.easing, easing, format!("{:?}", easing));
+                    }
+                });
+        }
+    });
+}
+
+//base colors of the level, objects using a channel preview them right away
+fn colors_panel(
+    mut egui_context: ResMut<EguiContext>,
+    mut editor_state: ResMut<EditorState>,
+    mut color_channels: ResMut<ColorChannels>,
+) {
+    if !editor_state.active {
+        return;
+    }
+
+    egui::Window::new("Colors").show(egui_context.ctx_mut(), |ui| {
+        for channel in [BACKGROUND_CHANNEL, GROUND_CHANNEL, LINE_CHANNEL] {
+            ui.horizontal(|ui| {
+                let mut color = color_channels.color(channel);
+                ui.label(channel_name(channel));
+                if ui.color_edit_button_rgb(&mut color).changed() {
+                    color_channels.set_base(channel, color);
+                }
+            });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let mut color = color_channels.color(editor_state.picked_channel);
+            ui.add(egui::DragValue::new(&mut editor_state.picked_channel).clamp_range(1..=MAX_USER_CHANNEL));
+            if ui.color_edit_button_rgb(&mut color).changed() {
+                color_channels.set_base(editor_state.picked_channel, color);
             }
+        });
+    });
+}
+
+fn draw_selection(
+    mut lines: ResMut<DebugLines>,
+    object_query: Query<&Transform, With<LevelObject>>,
+    editor_state: Res<EditorState>,
+) {
+    if !editor_state.active {
+        return;
+    }
+
+    if let Some(transform) = editor_state.selected.and_then(|selected| object_query.get(selected).ok()) {
+        let half = BLOCK_SIZE / 2.0;
+        let center = transform.translation;
+        let corners = [
+            center + Vec3::new(-half, -half, 0.0),
+            center + Vec3::new(half, -half, 0.0),
+            center + Vec3::new(half, half, 0.0),
+            center + Vec3::new(-half, half, 0.0),
+        ];
+        for i in 0..corners.len() {
+            lines.line_colored(corners[i], corners[(i + 1) % corners.len()], 0.0, Color::YELLOW);
         }
     }
 }
@@ -244,6 +649,9 @@ fn spawn_button(mut commands: Commands, game_assets: Res<GameAssets>, game_state
                 style: Style {
                     size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
                     justify_content: JustifyContent::Center,
+                    //the palette wraps into more rows at the bottom of the screen
+                    flex_wrap: FlexWrap::Wrap,
+                    align_content: AlignContent::FlexEnd,
                     ..default()
                 },
                 ..default()
@@ -251,21 +659,24 @@ fn spawn_button(mut commands: Commands, game_assets: Res<GameAssets>, game_state
             .insert(Name::new("Node"))
             .insert(NodeForBlockPlacingButtonsMarker)
             .with_children(|commands| {
-                for i in 0..3 {
-                    commands
-                        .spawn(ButtonBundle {
-                            style: Style {
-                                size: Size::new(
-                                    Val::Percent(15.0 * 9.0 / 16.0),
-                                    Val::Percent(15.0),
-                                ),
-                                align_self: AlignSelf::FlexEnd,
-                                margin: UiRect::all(Val::Percent(2.0)),
-                                ..default()
-                            },
-                            image: game_assets.blocks[i as usize].clone().into(),
+                for i in 0..PALETTE_SIZE {
+                    let mut button = ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(36.0), Val::Px(36.0)),
+                            align_self: AlignSelf::FlexEnd,
+                            margin: UiRect::all(Val::Px(4.0)),
                             ..default()
-                        })
+                        },
+                        ..default()
+                    };
+                    if i < 3 {
+                        button.image = game_assets.blocks[i as usize].clone().into();
+                    } else {
+                        button.background_color = portal_color(&palette_block(i, (0, 0))).into();
+                    }
+
+                    commands
+                        .spawn(button)
                         .insert(BlockButton { id: i })
                         .insert(Name::new("Button"));
                 }
@@ -273,34 +684,372 @@ fn spawn_button(mut commands: Commands, game_assets: Res<GameAssets>, game_state
     }
 }
 
+//the level as it is being edited, the way it gets saved
+pub fn edited_level(
+    editor_state: &EditorState,
+    object_query: &Query<&LevelObject>,
+    color_channels: &ColorChannels,
+) -> LevelFile {
+    let mut header = editor_state.header.clone();
+    //the music panel always fills in a song, only keep it if a file was picked
+    if header.music.as_ref().map_or(false, |music| music.path.is_empty()) {
+        header.music = None;
+    }
+
+    LevelFile {
+        header,
+        blocks: object_query.iter().map(|object| object.info.clone()).collect(),
+        colors: color_channels.base_colors(),
+    }
+}
+
+//for comparing two levels, the objects come out of queries grouped by their components instead of in file order
+fn level_contents(level: &LevelFile) -> Vec<String> {
+    let mut blocks: Vec<String> = level
+        .blocks
+        .iter()
+        .map(|block| serde_json::to_string(block).unwrap_or_default())
+        .collect();
+    blocks.sort();
+    let mut colors: Vec<String> = level
+        .colors
+        .iter()
+        .map(|color| serde_json::to_string(color).unwrap_or_default())
+        .collect();
+    colors.sort();
+
+    let mut contents = vec![serde_json::to_string(&level.header).unwrap_or_default()];
+    contents.extend(blocks);
+    contents.extend(colors);
+    contents
+}
+
+//whether the level was changed since it was opened or last saved
+pub fn has_unsaved_changes(
+    editor_state: &EditorState,
+    object_query: &Query<&LevelObject>,
+    color_channels: &ColorChannels,
+) -> bool {
+    editor_state.active
+        && level_contents(&edited_level(editor_state, object_query, color_channels)) != editor_state.saved_contents
+}
+
 fn save_level(
-    keyboard: Res<Input<KeyCode>>,
-){
-    if keyboard.just_pressed(KeyCode::O){
-        let block_info_vec = vec![
-            BlockInfo {
-                id: 0,
-                name: "Spike0".to_string(),
-                marker_type: 0,
-                coords: (0, -256)
-            },
-            BlockInfo {
-                id: 0,
-                name: "Spike0".to_string(),
-                marker_type: 0,
-                coords: (64, -256)
-            },
-            BlockInfo {
-                id: 0,
-                name: "Spike0".to_string(),
-                marker_type: 0,
-                coords: (128, -256)
-            },
-        ];
-    
-        let file = std::fs::File::create("test_map.json").unwrap();
-        serde_json::to_writer_pretty(file, &block_info_vec).unwrap();
+    actions: Res<ActionState>,
+    mut save_level_ev: EventReader<SaveLevelEvent>,
+    object_query: Query<&LevelObject>,
+    game_state: Res<GameState>,
+    mut editor_state: ResMut<EditorState>,
+    color_channels: Res<ColorChannels>,
+) {
+    let requested = save_level_ev.iter().last().is_some();
+    if editor_state.active && (actions.just_pressed(InputAction::EditorSave) || requested) {
+        let level = edited_level(&editor_state, &object_query, &color_channels);
+        crate::level::save_level(&game_state.level_path, &level);
+        editor_state.saved_contents = level_contents(&level);
+        editor_state.status = format!("Saved {}", game_state.level_path);
+    }
+}
+
+//save [path], without a path the level is saved where it was opened from
+fn save_command(
+    mut command_ev: EventReader<ConsoleCommandEvent>,
+    mut reply_ev: EventWriter<ConsoleReply>,
+    mut save_level_ev: EventWriter<SaveLevelEvent>,
+    mut game_state: ResMut<GameState>,
+    editor_state: Res<EditorState>,
+) {
+    for command in command_ev.iter().filter(|command| command.name == "save") {
+        if !editor_state.active {
+            reply_ev.send(ConsoleReply::new("Levels can only be saved from the editor"));
+            continue;
+        }
+        if let Some(path) = command.args.first() {
+            game_state.level_path = path.clone();
+        }
+        save_level_ev.send_default();
+        reply_ev.send(ConsoleReply(format!("Saving {}", game_state.level_path)));
+    }
+}
+
+//spawn <id> <x> <y>, the id is the palette button's
+fn spawn_command(
+    mut commands: Commands,
+    mut command_ev: EventReader<ConsoleCommandEvent>,
+    mut reply_ev: EventWriter<ConsoleReply>,
+    game_assets: Res<GameAssets>,
+    game_state: Res<GameState>,
+    mut level_state: ResMut<LevelState>,
+    object_query: Query<&LevelObject>,
+) {
+    for command in command_ev.iter().filter(|command| command.name == "spawn") {
+        if game_state.variant == GameStateVariant::Menu {
+            reply_ev.send(ConsoleReply::new("Only works in a level or the editor"));
+            continue;
+        }
+        let numbers: Vec<i32> = command.args.iter().filter_map(|arg| arg.parse().ok()).collect();
+        let (id, x, y) = match numbers[..] {
+            [id, x, y] if command.args.len() == 3 && (0..PALETTE_SIZE as i32).contains(&id) => (id as u8, x, y),
+            _ => {
+                reply_ev.send(ConsoleReply(format!(
+                    "Usage: spawn <id> <x> <y>, with an id below {}",
+                    PALETTE_SIZE
+                )));
+                continue;
+            }
+        };
+
+        let mut block_info = palette_block(id, (x, y));
+        if !number_coin(&mut block_info, &object_query) {
+            reply_ev.send(ConsoleReply(format!("A level can't have more than {} coins", MAX_COINS)));
+            continue;
+        }
+        spawn_block(&mut commands, &game_assets, &block_info);
+        if level_state.active {
+            level_state.spawned = true;
+            level_state.cheated = true;
+        }
+        reply_ev.send(ConsoleReply(format!("Spawned {} at ({}, {})", block_info.name, x, y)));
+    }
+}
+
+fn change_starting_speed(actions: Res<ActionState>, mut editor_state: ResMut<EditorState>) {
+    if !editor_state.active {
+        return;
+    }
+
+    let index = SpeedTier::ALL
+        .iter()
+        .position(|tier| *tier == editor_state.header.starting_speed)
+        .unwrap();
+
+    if actions.just_pressed(InputAction::EditorSpeedUp) && index + 1 < SpeedTier::ALL.len() {
+        editor_state.header.starting_speed = SpeedTier::ALL[index + 1];
+    }
+    if actions.just_pressed(InputAction::EditorSpeedDown) && index > 0 {
+        editor_state.header.starting_speed = SpeedTier::ALL[index - 1];
+    }
+}
+
+fn editor_speed_sections(
+    portal_query: &Query<(&Transform, &SpeedPortal)>,
+    starting_speed: SpeedTier,
+) -> Vec<(f32, SpeedTier)> {
+    speed_sections(
+        starting_speed,
+        portal_query
+            .iter()
+            .map(|(transform, portal)| (transform.translation.x, portal.tier)),
+    )
+}
+
+//one vertical line for every second of play, so you can see where the player will be
+fn draw_speed_guidelines(
+    mut lines: ResMut<DebugLines>,
+    portal_query: Query<(&Transform, &SpeedPortal)>,
+    editor_state: Res<EditorState>,
+) {
+    if !editor_state.active {
+        return;
+    }
+
+    let sections = editor_speed_sections(&portal_query, editor_state.header.starting_speed);
+    for second in 1..=GUIDELINE_SECONDS {
+        let (x, tier) = x_at_time(&sections, second as f32);
+        lines.line_colored(
+            Vec3::new(x, -1000.0, 0.0),
+            Vec3::new(x, 1000.0, 0.0),
+            0.0,
+            tier.color(),
+        );
+    }
+}
+
+//what the level select shows about the level
+fn level_panel(
+    mut egui_context: ResMut<EguiContext>,
+    mut editor_state: ResMut<EditorState>,
+    mut game_state: ResMut<GameState>,
+    mut save_level_ev: EventWriter<SaveLevelEvent>,
+) {
+    if !editor_state.active {
+        return;
     }
+
+    egui::Window::new("Level").show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut editor_state.header.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Author");
+            ui.text_edit_singleline(&mut editor_state.header.author);
+        });
+        egui::ComboBox::from_label("Difficulty")
+            .selected_text(format!("{:?}", editor_state.header.difficulty))
+            .show_ui(ui, |ui| {
+                for difficulty in Difficulty::ALL {
+                    ui.selectable_value(&mut editor_state.header.difficulty, difficulty, format!("{:?}", difficulty));
+                }
+            });
+        //also changed with the editor speed keys
+        egui::ComboBox::from_label("Starting speed")
+            .selected_text(format!("{:?}", editor_state.header.starting_speed))
+            .show_ui(ui, |ui| {
+                for tier in SpeedTier::ALL {
+                    ui.selectable_value(&mut editor_state.header.starting_speed, tier, format!("{:?}", tier));
+                }
+            });
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                save_level_ev.send_default();
+            }
+            if ui.button("Exit to menu").clicked() {
+                game_state.variant = GameStateVariant::Menu;
+            }
+        });
+        if !editor_state.status.is_empty() {
+            ui.label(editor_state.status.as_str());
+        }
+    });
+}
+
+fn music_panel(
+    mut egui_context: ResMut<EguiContext>,
+    mut editor_state: ResMut<EditorState>,
+    asset_server: Res<AssetServer>,
+    sinks: Res<Assets<AudioSink>>,
+    portal_query: Query<(&Transform, &SpeedPortal)>,
+    camera_query: Query<&Transform, With<EditorCameraMarker>>,
+    mut music_player: ResMut<MusicPlayer>,
+) {
+    if !editor_state.active {
+        return;
+    }
+
+    let mut play = false;
+    let mut stop = false;
+    egui::Window::new("Music").show(egui_context.ctx_mut(), |ui| {
+        let music = editor_state.header.music.get_or_insert_with(LevelMusic::default);
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut music.path);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Offset");
+            ui.add(
+                egui::DragValue::new(&mut music.offset)
+                    .speed(0.01)
+                    .clamp_range(0.0..=600.0)
+                    .suffix("s"),
+            );
+        });
+        ui.horizontal(|ui| {
+            play = ui.button("Play from camera").clicked();
+            stop = ui.button("Stop").clicked();
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let mut bpm = editor_state.header.bpm.unwrap_or(0.0);
+            ui.label("BPM");
+            if ui.add(egui::DragValue::new(&mut bpm).speed(0.1).clamp_range(0.0..=1000.0)).changed() {
+                //0 turns the beat guidelines off
+                editor_state.header.bpm = if bpm > 0.0 { Some(bpm) } else { None };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("First beat");
+            ui.add(
+                egui::DragValue::new(&mut editor_state.header.beat_offset)
+                    .speed(0.01)
+                    .clamp_range(0.0..=600.0)
+                    .suffix("s"),
+            );
+        });
+        ui.label(format!("{} tapped guidelines (T while playing)", editor_state.header.guidelines.len()));
+        if ui.button("Clear guidelines").clicked() {
+            editor_state.header.guidelines.clear();
+        }
+    });
+
+    if play {
+        let sections = editor_speed_sections(&portal_query, editor_state.header.starting_speed);
+        let camera_x = camera_query
+            .iter()
+            .next()
+            .map(|transform| transform.translation.x)
+            .unwrap_or(STARTING_PLAYER_POSTION.x);
+        music_player.set_music(editor_state.header.music.as_ref(), &asset_server);
+        music_player.play_from(time_at_x(&sections, camera_x));
+    }
+    if stop {
+        music_player.stop(&sinks);
+    }
+}
+
+//lines where the song's beats fall, brighter at the start of every bar
+fn draw_beat_guidelines(
+    mut lines: ResMut<DebugLines>,
+    portal_query: Query<(&Transform, &SpeedPortal)>,
+    object_query: Query<&LevelObject>,
+    editor_state: Res<EditorState>,
+) {
+    if !editor_state.active {
+        return;
+    }
+
+    let sections = editor_speed_sections(&portal_query, editor_state.header.starting_speed);
+    if let Some(bpm) = editor_state.header.bpm {
+        //beats are drawn up to the end of the level as it is being edited
+        let blocks: Vec<&BlockInfo> = object_query.iter().map(|object| &object.info).collect();
+        let duration = time_at_x(&sections, blocks_end(blocks.iter().copied()));
+        let beat_length = 60.0 / bpm;
+        let mut beat = 0;
+        let mut beat_time = editor_state.header.beat_offset;
+        while beat_time <= duration {
+            let (x, _) = x_at_time(&sections, beat_time);
+            let color = if beat % BEATS_PER_BAR == 0 { Color::YELLOW } else { Color::GRAY };
+            lines.line_colored(Vec3::new(x, -800.0, 0.0), Vec3::new(x, 800.0, 0.0), 0.0, color);
+            beat += 1;
+            beat_time = editor_state.header.beat_offset + beat as f32 * beat_length;
+        }
+    }
+
+    for guideline in editor_state.header.guidelines.iter() {
+        let (x, _) = x_at_time(&sections, *guideline);
+        lines.line_colored(Vec3::new(x, -800.0, 0.0), Vec3::new(x, 800.0, 0.0), 0.0, Color::LIME_GREEN);
+    }
+}
+
+//tapping along to the song drops a guideline where the player would be
+fn tap_guidelines(
+    actions: Res<ActionState>,
+    time: Res<Time>,
+    music_player: Res<MusicPlayer>,
+    mut editor_state: ResMut<EditorState>,
+) {
+    if editor_state.active && music_player.is_playing() && actions.just_pressed(InputAction::EditorTapGuideline) {
+        let position = music_player.position(&time);
+        editor_state.header.guidelines.push(position);
+    }
+}
+
+//shows where the player would be at the moment of the song that is playing
+fn draw_music_playhead(
+    mut lines: ResMut<DebugLines>,
+    portal_query: Query<(&Transform, &SpeedPortal)>,
+    editor_state: Res<EditorState>,
+    music_player: Res<MusicPlayer>,
+    time: Res<Time>,
+) {
+    if !editor_state.active || !music_player.is_playing() {
+        return;
+    }
+
+    let sections = editor_speed_sections(&portal_query, editor_state.header.starting_speed);
+    let (x, _) = x_at_time(&sections, music_player.position(&time));
+    lines.line_colored(Vec3::new(x, -1000.0, 0.0), Vec3::new(x, 1000.0, 0.0), 0.0, Color::WHITE);
 }
 
 pub struct EditorPlugin;
@@ -308,13 +1057,30 @@ impl Plugin for EditorPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(DebugLinesPlugin::default())
             .add_event::<MouseWheel>()
-            .add_system(editor_open)
+            .add_event::<SaveLevelEvent>()
+            .add_console_command("save", "save [path] - saves the edited level, to the given path if there is one")
+            .add_console_command("spawn", "spawn <id> <x> <y> - places the palette object with the id")
+            .add_system(editor_open.before(editor_close))
             .add_system(editor_close)
             .add_system(camera_movement)
             .add_system(camera_zoom)
             .add_system(place_blocks)
             //.add_system(draw_editor_lines)
             .add_system(save_level)
-            .add_system(button_clicked.after(place_blocks));
+            .add_system(change_starting_speed)
+            .add_system(draw_speed_guidelines)
+            .add_system(level_panel)
+            .add_system(music_panel)
+            .add_system(draw_music_playhead)
+            .add_system(draw_beat_guidelines)
+            .add_system(tap_guidelines)
+            .add_system(select_object)
+            .add_system(property_panel.after(select_object))
+            .add_system(delete_selected.after(property_panel))
+            .add_system(colors_panel)
+            .add_system(draw_selection)
+            .add_system(button_clicked.after(place_blocks))
+            .add_system(save_command.before(save_level))
+            .add_system(spawn_command);
     }
 }
@@ -0,0 +1,59 @@
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+use crate::GameAssets;
+
+#[derive(Component)]
+struct FpsTextMarker;
+
+fn spawn_fps_text(mut commands: Commands, game_assets: Res<GameAssets>) {
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: game_assets.font_roboto_black.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(FpsTextMarker)
+        .insert(Name::new("FpsText"));
+}
+
+fn update_fps_text(
+    diagnostics: Res<Diagnostics>,
+    settings: Res<Settings>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<FpsTextMarker>>,
+) {
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed());
+
+    for (mut text, mut visibility) in text_query.iter_mut() {
+        visibility.is_visible = settings.show_fps;
+        if let Some(fps) = fps.filter(|_| settings.show_fps) {
+            text.sections[0].value = format!("{:.0} FPS", fps);
+        }
+    }
+}
+
+pub struct FpsPlugin;
+impl Plugin for FpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_startup_system(spawn_fps_text)
+            .add_system(update_fps_text);
+    }
+}
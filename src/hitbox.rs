@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::DebugLines;
+use bevy_rapier2d::prelude::*;
+
+use crate::input::{ActionState, InputAction};
+use crate::level::SpikeMarker;
+use crate::player::{LevelState, PlayerMarker, RespawnPlayerEvent};
+use crate::settings::{Settings, SettingsChangedEvent};
+
+const TRAIL_LENGTH: usize = 600; //positions, about ten seconds at 60 fps
+const DEATH_MARK_SIZE: f32 = 12.0;
+
+const SOLID_COLOR: Color = Color::BLUE;
+const HAZARD_COLOR: Color = Color::RED;
+const SENSOR_COLOR: Color = Color::YELLOW;
+const PLAYER_COLOR: Color = Color::LIME_GREEN;
+const TRAIL_COLOR: Color = Color::WHITE;
+const DEATH_COLOR: Color = Color::ORANGE_RED;
+
+//where the players were during the attempt, the oldest position comes first
+#[derive(Resource, Default)]
+struct PlayerTrail {
+    players: Vec<VecDeque<Vec3>>,
+}
+
+fn toggle_hitboxes(
+    actions: Res<ActionState>,
+    mut settings: ResMut<Settings>,
+    mut settings_changed_ev: EventWriter<SettingsChangedEvent>,
+) {
+    if actions.just_pressed(InputAction::ToggleHitboxes) {
+        settings.show_hitboxes = !settings.show_hitboxes;
+        settings_changed_ev.send_default();
+    }
+}
+
+fn record_player_trail(
+    mut respawn_player_ev: EventReader<RespawnPlayerEvent>,
+    player_query: Query<&Transform, With<PlayerMarker>>,
+    level_state: Res<LevelState>,
+    mut trail: ResMut<PlayerTrail>,
+) {
+    if respawn_player_ev.iter().last().is_some() || !level_state.active {
+        trail.players.clear();
+        return;
+    }
+    if level_state.paused || level_state.death_timer.is_some() {
+        return;
+    }
+
+    //a dual portal adds a player mid attempt
+    let count = player_query.iter().count();
+    trail.players.resize_with(count, VecDeque::new);
+    for (positions, transform) in trail.players.iter_mut().zip(player_query.iter()) {
+        if positions.len() == TRAIL_LENGTH {
+            positions.pop_front();
+        }
+        positions.push_back(transform.translation);
+    }
+}
+
+fn draw_cuboid(lines: &mut DebugLines, transform: &GlobalTransform, half_extents: Vec2, color: Color) {
+    let corners = [
+        Vec3::new(-half_extents.x, -half_extents.y, 0.0),
+        Vec3::new(half_extents.x, -half_extents.y, 0.0),
+        Vec3::new(half_extents.x, half_extents.y, 0.0),
+        Vec3::new(-half_extents.x, half_extents.y, 0.0),
+    ]
+    .map(|corner| transform.transform_point(corner));
+
+    for i in 0..4 {
+        lines.line_colored(corners[i], corners[(i + 1) % 4], 0.0, color);
+    }
+}
+
+//colored by what touching the collider does
+fn draw_hitboxes(
+    mut lines: ResMut<DebugLines>,
+    collider_query: Query<(
+        &GlobalTransform,
+        &Collider,
+        Option<&Sensor>,
+        Option<&SpikeMarker>,
+        Option<&PlayerMarker>,
+    )>,
+    settings: Res<Settings>,
+) {
+    if !settings.show_hitboxes {
+        return;
+    }
+
+    for (transform, collider, sensor, spike, player) in collider_query.iter() {
+        let color = if player.is_some() {
+            PLAYER_COLOR
+        } else if spike.is_some() {
+            HAZARD_COLOR
+        } else if sensor.is_some() {
+            SENSOR_COLOR
+        } else {
+            SOLID_COLOR
+        };
+
+        if let Some(cuboid) = collider.as_cuboid() {
+            draw_cuboid(&mut lines, transform, cuboid.half_extents(), color);
+        }
+    }
+}
+
+fn draw_trail_and_death(
+    mut lines: ResMut<DebugLines>,
+    trail: Res<PlayerTrail>,
+    level_state: Res<LevelState>,
+    settings: Res<Settings>,
+) {
+    if !settings.show_hitboxes || !level_state.active {
+        return;
+    }
+
+    for positions in trail.players.iter() {
+        for (start, end) in positions.iter().zip(positions.iter().skip(1)) {
+            lines.line_colored(*start, *end, 0.0, TRAIL_COLOR);
+        }
+    }
+
+    //stays until the level is closed, so the last death can be looked at during the next attempt
+    if let Some(position) = level_state.death_position {
+        let size = DEATH_MARK_SIZE;
+        lines.line_colored(
+            position + Vec3::new(-size, -size, 0.0),
+            position + Vec3::new(size, size, 0.0),
+            0.0,
+            DEATH_COLOR,
+        );
+        lines.line_colored(
+            position + Vec3::new(-size, size, 0.0),
+            position + Vec3::new(size, -size, 0.0),
+            0.0,
+            DEATH_COLOR,
+        );
+    }
+}
+
+pub struct HitboxPlugin;
+impl Plugin for HitboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerTrail>()
+            .add_system(toggle_hitboxes)
+            .add_system(record_player_trail)
+            .add_system(draw_hitboxes)
+            .add_system(draw_trail_and_death);
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use bevy_egui::EguiContext;
+
+use crate::player::RespawnPlayerEvent;
+use crate::settings::{Settings, SettingsWindow};
+
+//how long a press waits to be used by default, in seconds
+pub const DEFAULT_BUFFER_WINDOW: f32 = 0.1;
+
+//what the game reacts to, the keys behind them come from the bindings
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InputAction {
+    Jump,
+    Pause,
+    Restart,
+    TogglePractice,
+    PlaceCheckpoint,
+    DeleteCheckpoint,
+    EditorLeft,
+    EditorRight,
+    EditorUp,
+    EditorDown,
+    EditorPlace,
+    EditorSelect,
+    EditorDelete,
+    EditorSave,
+    EditorSpeedUp,
+    EditorSpeedDown,
+    EditorTapGuideline,
+    ToggleHitboxes,
+    ToggleConsole,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 19] = [
+        InputAction::Jump,
+        InputAction::Pause,
+        InputAction::Restart,
+        InputAction::TogglePractice,
+        InputAction::PlaceCheckpoint,
+        InputAction::DeleteCheckpoint,
+        InputAction::EditorLeft,
+        InputAction::EditorRight,
+        InputAction::EditorUp,
+        InputAction::EditorDown,
+        InputAction::EditorPlace,
+        InputAction::EditorSelect,
+        InputAction::EditorDelete,
+        InputAction::EditorSave,
+        InputAction::EditorSpeedUp,
+        InputAction::EditorSpeedDown,
+        InputAction::EditorTapGuideline,
+        InputAction::ToggleHitboxes,
+        InputAction::ToggleConsole,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::Jump => "Jump",
+            InputAction::Pause => "Pause",
+            InputAction::Restart => "Restart",
+            InputAction::TogglePractice => "Toggle practice",
+            InputAction::PlaceCheckpoint => "Place checkpoint",
+            InputAction::DeleteCheckpoint => "Delete checkpoint",
+            InputAction::EditorLeft => "Editor: move left",
+            InputAction::EditorRight => "Editor: move right",
+            InputAction::EditorUp => "Editor: move up",
+            InputAction::EditorDown => "Editor: move down",
+            InputAction::EditorPlace => "Editor: place",
+            InputAction::EditorSelect => "Editor: select",
+            InputAction::EditorDelete => "Editor: delete",
+            InputAction::EditorSave => "Editor: save",
+            InputAction::EditorSpeedUp => "Editor: faster start",
+            InputAction::EditorSpeedDown => "Editor: slower start",
+            InputAction::EditorTapGuideline => "Editor: tap guideline",
+            InputAction::ToggleHitboxes => "Toggle hitboxes",
+            InputAction::ToggleConsole => "Toggle console",
+        }
+    }
+
+    fn default_bindings(&self) -> Vec<Binding> {
+        match self {
+            InputAction::Jump => vec![
+                Binding::Key(KeyCode::Space),
+                Binding::Key(KeyCode::Up),
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButtonType::South),
+            ],
+            InputAction::Pause => vec![Binding::Key(KeyCode::Escape), Binding::Gamepad(GamepadButtonType::Start)],
+            InputAction::Restart => vec![Binding::Key(KeyCode::R), Binding::Gamepad(GamepadButtonType::Select)],
+            InputAction::TogglePractice => vec![Binding::Key(KeyCode::P), Binding::Gamepad(GamepadButtonType::North)],
+            InputAction::PlaceCheckpoint => vec![Binding::Key(KeyCode::Z), Binding::Gamepad(GamepadButtonType::West)],
+            InputAction::DeleteCheckpoint => vec![Binding::Key(KeyCode::X), Binding::Gamepad(GamepadButtonType::East)],
+            InputAction::EditorLeft => vec![Binding::Key(KeyCode::A)],
+            InputAction::EditorRight => vec![Binding::Key(KeyCode::D)],
+            InputAction::EditorUp => vec![Binding::Key(KeyCode::W)],
+            InputAction::EditorDown => vec![Binding::Key(KeyCode::S)],
+            InputAction::EditorPlace => vec![Binding::Mouse(MouseButton::Left)],
+            InputAction::EditorSelect => vec![Binding::Mouse(MouseButton::Right)],
+            InputAction::EditorDelete => vec![Binding::Key(KeyCode::Delete)],
+            InputAction::EditorSave => vec![Binding::Key(KeyCode::O)],
+            InputAction::EditorSpeedUp => vec![Binding::Key(KeyCode::E)],
+            InputAction::EditorSpeedDown => vec![Binding::Key(KeyCode::Q)],
+            InputAction::EditorTapGuideline => vec![Binding::Key(KeyCode::T)],
+            InputAction::ToggleHitboxes => vec![Binding::Key(KeyCode::H)],
+            InputAction::ToggleConsole => vec![Binding::Key(KeyCode::Grave)],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType), //any connected gamepad
+}
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse {:?}", button),
+            Binding::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct InputBindings {
+    #[serde(default)]
+    actions: HashMap<InputAction, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            actions: InputAction::ALL
+                .iter()
+                .map(|action| (*action, action.default_bindings()))
+                .collect(),
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn bindings(&self, action: InputAction) -> &[Binding] {
+        self.actions.get(&action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn bind(&mut self, action: InputAction, binding: Binding) {
+        let bindings = self.actions.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: InputAction, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(&action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    //actions added after the file was written get their default bindings
+    pub fn fill_missing(&mut self) {
+        for action in InputAction::ALL {
+            self.actions.entry(action).or_insert_with(|| action.default_bindings());
+        }
+    }
+}
+
+//the actions' state this frame, systems read this instead of the raw inputs
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<InputAction>,
+    just_pressed: HashSet<InputAction>,
+    ui_clicks: HashSet<MouseButton>, //pressed on a button or a window, ignored until they're released
+    captured: HashSet<Binding>,      //pressed while rebinding an action, ignored until they're released
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+struct BufferedPress {
+    action: InputAction,
+    time: f64, //the simulation clock of the frame it was pressed on, it stops while paused
+}
+
+//presses that weren't used yet, so a press slightly too early isn't lost
+#[derive(Resource)]
+pub struct InputBuffer {
+    pub window: f32,
+    presses: Vec<BufferedPress>,
+}
+
+impl InputBuffer {
+    pub fn new(window: f32) -> Self {
+        Self {
+            window,
+            presses: Vec::new(),
+        }
+    }
+
+    //uses up the oldest press of the action that is still inside the window
+    pub fn consume(&mut self, action: InputAction, time: &Time) -> bool {
+        let now = time.elapsed_seconds_f64();
+        let window = self.window as f64;
+        match self
+            .presses
+            .iter()
+            .position(|press| press.action == action && now - press.time <= window)
+        {
+            Some(index) => {
+                self.presses.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.presses.clear();
+    }
+
+    //presses made while the game is paused would fire as soon as it resumes, so they aren't kept
+    fn record(&mut self, action_state: &ActionState, time: &Time) {
+        if time.is_paused() {
+            return;
+        }
+
+        let now = time.elapsed_seconds_f64();
+        let window = self.window as f64;
+        self.presses.retain(|press| now - press.time <= window);
+
+        for action in InputAction::ALL {
+            if action_state.just_pressed(action) {
+                self.presses.push(BufferedPress { action, time: now });
+            }
+        }
+    }
+}
+
+fn binding_state(
+    binding: &Binding,
+    keys: &Input<KeyCode>,
+    mouse_buttons: &Input<MouseButton>,
+    gamepad_buttons: &Input<GamepadButton>,
+    gamepads: &Gamepads,
+) -> (bool, bool) {
+    match binding {
+        Binding::Key(key) => (keys.pressed(*key), keys.just_pressed(*key)),
+        Binding::Mouse(button) => (mouse_buttons.pressed(*button), mouse_buttons.just_pressed(*button)),
+        Binding::Gamepad(button_type) => gamepads.iter().fold((false, false), |(pressed, just_pressed), gamepad| {
+            let button = GamepadButton::new(gamepad, *button_type);
+            (
+                pressed || gamepad_buttons.pressed(button),
+                just_pressed || gamepad_buttons.just_pressed(button),
+            )
+        }),
+    }
+}
+
+fn update_action_state(
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    settings: Res<Settings>,
+    settings_window: Res<SettingsWindow>,
+    mut egui_context: ResMut<EguiContext>,
+    interaction_query: Query<&Interaction>,
+    mut action_state: ResMut<ActionState>,
+) {
+    action_state.pressed.clear();
+    action_state.just_pressed.clear();
+
+    //typing into a text field or clicking a window or a menu button isn't meant for the game,
+    //the click would otherwise also jump right as the resume or replay button starts the game
+    let ctx = egui_context.ctx_mut();
+    let keyboard_taken = ctx.wants_keyboard_input();
+    let pointer_taken =
+        ctx.wants_pointer_input() || interaction_query.iter().any(|interaction| *interaction != Interaction::None);
+    if pointer_taken {
+        action_state.ui_clicks.extend(mouse_buttons.get_just_pressed());
+    }
+    action_state.ui_clicks.retain(|button| mouse_buttons.pressed(*button));
+    //the button that gets bound is meant for the settings window, not for the action it's bound to
+    if settings_window.listening.is_some() {
+        let captured = &mut action_state.captured;
+        captured.extend(keys.get_pressed().map(|key| Binding::Key(*key)));
+        captured.extend(mouse_buttons.get_pressed().map(|button| Binding::Mouse(*button)));
+        captured.extend(gamepad_buttons.get_pressed().map(|button| Binding::Gamepad(button.button_type)));
+    }
+    action_state
+        .captured
+        .retain(|binding| binding_state(binding, &keys, &mouse_buttons, &gamepad_buttons, &gamepads).0);
+
+    for action in InputAction::ALL {
+        for binding in settings.bindings.bindings(action) {
+            let (pressed, just_pressed) =
+                binding_state(binding, &keys, &mouse_buttons, &gamepad_buttons, &gamepads);
+            match binding {
+                _ if action_state.captured.contains(binding) => continue,
+                Binding::Key(_) if keyboard_taken => continue,
+                Binding::Mouse(_) if pointer_taken => continue,
+                Binding::Mouse(button) if action_state.ui_clicks.contains(button) => continue,
+                _ => {}
+            }
+            if pressed {
+                action_state.pressed.insert(action);
+            }
+            if just_pressed {
+                action_state.just_pressed.insert(action);
+            }
+        }
+    }
+}
+
+fn buffer_presses(time: Res<Time>, action_state: Res<ActionState>, mut buffer: ResMut<InputBuffer>) {
+    buffer.record(&action_state, &time);
+}
+
+fn clear_input_buffer(mut respawn_player_ev: EventReader<RespawnPlayerEvent>, mut buffer: ResMut<InputBuffer>) {
+    if respawn_player_ev.iter().last().is_some() {
+        buffer.clear();
+    }
+}
+
+//the first button pressed this frame, used when rebinding
+pub fn first_just_pressed(
+    keys: &Input<KeyCode>,
+    mouse_buttons: &Input<MouseButton>,
+    gamepad_buttons: &Input<GamepadButton>,
+) -> Option<Binding> {
+    keys.get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| mouse_buttons.get_just_pressed().next().map(|button| Binding::Mouse(*button)))
+        .or_else(|| {
+            gamepad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Gamepad(button.button_type))
+        })
+}
+
+pub struct InputActionPlugin;
+impl Plugin for InputActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .add_system_to_stage(CoreStage::PreUpdate, update_action_state.after(InputSystem).after(UiSystem::Focus))
+            .add_system_to_stage(CoreStage::PreUpdate, buffer_presses.after(update_action_state))
+            .add_system(clear_input_buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::{Duration, Instant};
+
+    fn time_at(seconds: f64) -> Time {
+        let startup = Instant::now();
+        let mut time = Time::new(startup);
+        time.update_with_instant(startup + Duration::from_secs_f64(seconds));
+        time
+    }
+
+    fn jump_pressed() -> ActionState {
+        let mut action_state = ActionState::default();
+        action_state.pressed.insert(InputAction::Jump);
+        action_state.just_pressed.insert(InputAction::Jump);
+        action_state
+    }
+
+    #[test]
+    fn consume_inside_window() {
+        let mut buffer = InputBuffer::new(0.1);
+        buffer.record(&jump_pressed(), &time_at(1.0));
+
+        assert!(buffer.consume(InputAction::Jump, &time_at(1.05)));
+        assert!(!buffer.consume(InputAction::Jump, &time_at(1.05)), "a press is only used once");
+    }
+
+    #[test]
+    fn consume_outside_window() {
+        let mut buffer = InputBuffer::new(0.1);
+        buffer.record(&jump_pressed(), &time_at(1.0));
+
+        assert!(!buffer.consume(InputAction::Jump, &time_at(1.2)));
+        assert!(!buffer.consume(InputAction::Pause, &time_at(1.0)), "only the pressed action is buffered");
+    }
+
+    #[test]
+    fn consume_takes_oldest_press_first() {
+        let mut buffer = InputBuffer::new(0.1);
+        buffer.record(&jump_pressed(), &time_at(1.0));
+        buffer.record(&jump_pressed(), &time_at(1.05));
+
+        assert!(buffer.consume(InputAction::Jump, &time_at(1.08)));
+        assert_eq!(buffer.presses.len(), 1);
+        assert!((buffer.presses[0].time - 1.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn old_presses_are_dropped_when_recording() {
+        let mut buffer = InputBuffer::new(0.1);
+        buffer.record(&jump_pressed(), &time_at(1.0));
+        buffer.record(&ActionState::default(), &time_at(2.0));
+
+        assert!(buffer.presses.is_empty());
+    }
+
+    #[test]
+    fn presses_are_dropped_while_paused() {
+        let mut buffer = InputBuffer::new(0.1);
+        let mut time = time_at(1.0);
+        time.pause();
+        buffer.record(&jump_pressed(), &time);
+
+        assert!(buffer.presses.is_empty());
+        assert!(!buffer.consume(InputAction::Jump, &time));
+    }
+}
@@ -0,0 +1,717 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::coin::{Coin, MAX_COINS};
+use crate::color::{ChannelColor, ColorChannel};
+use crate::music::LevelMusic;
+use crate::pad::{JumpOrb, JumpPad, PadColor, UsedBy};
+use crate::player::{PlayerSize, SpeedTier, STARTING_PLAYER_POSTION};
+use crate::portal::{DualPortal, MirrorPortal, SizePortal, SpeedPortal};
+use crate::trigger::{GroupMember, Trigger, TriggerInfo, TriggerKind};
+use crate::{GameAssets, GroundMarker};
+
+pub const LEVELS_DIRECTORY: &str = "levels";
+pub const DEFAULT_LEVEL_PATH: &str = "levels/test_map.json"; //opened by the game and editor command line arguments
+const LEVEL_END_PADDING: f32 = 640.0; //used when the level has no explicit end marker
+const LEVEL_BOUNDS_PADDING: f32 = 1000.0; //how far above or below every object a player can go before dying
+pub const ASSETS_DIRECTORY: &str = "assets"; //where the asset server looks for files
+
+#[derive(Component)]
+pub struct SpikeMarker;
+#[derive(Component)]
+pub struct ClippedBlockMarker;
+#[derive(Component)]
+pub struct LevelEndMarker;
+
+#[derive(Component)]
+pub enum BlockType {
+    Spike(SpikeMarker),
+    Ground(GroundMarker),
+    Clipped(ClippedBlockMarker),
+}
+
+//objects that fire once per attempt, cleared again on respawn
+#[derive(Component)]
+pub struct Activated(pub bool);
+
+//every object spawned from (or saved into) a level file carries its own description
+#[derive(Component)]
+pub struct LevelObject {
+    pub info: BlockInfo,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Difficulty {
+    #[default]
+    Unrated,
+    Easy,
+    Normal,
+    Hard,
+    Harder,
+    Insane,
+    Demon,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 7] = [
+        Difficulty::Unrated,
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Harder,
+        Difficulty::Insane,
+        Difficulty::Demon,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LevelHeader {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    pub starting_speed: SpeedTier,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub music: Option<LevelMusic>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f32>,
+    #[serde(default)]
+    pub beat_offset: f32, //seconds into the level of the first beat
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guidelines: Vec<f32>, //tapped by the creator, in seconds into the level
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlockInfo {
+    pub id: u8,
+    pub name: String,
+    pub marker_type: u8, //0- spike, 1- block, 2- ClippedBlockMarker, 3- speed portal, 4- size portal, 5- mirror portal, 6- dual portal, 7- jump pad, 8- jump orb, 9- level end, 10- coin, 11- trigger
+    pub coords: (i32, i32),
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<SpeedTier>, //only used by speed portals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<PlayerSize>, //only used by size portals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrored: Option<bool>, //only used by mirror portals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dual: Option<bool>, //only used by dual portals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pad: Option<PadColor>, //only used by jump pads and orbs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coin: Option<u8>, //index of the coin, every coin in a level has its own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<u16>, //groups that triggers can target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<TriggerInfo>, //only used by triggers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_channel: Option<u16>, //objects without one keep their own color
+}
+
+impl BlockInfo {
+    pub fn new(id: u8, name: &str, marker_type: u8, coords: (i32, i32)) -> Self {
+        BlockInfo {
+            id,
+            name: name.to_string(),
+            marker_type,
+            coords,
+            speed: None,
+            size: None,
+            mirrored: None,
+            dual: None,
+            pad: None,
+            coin: None,
+            groups: Vec::new(),
+            trigger: None,
+            color_channel: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct LevelFile {
+    #[serde(default)]
+    pub header: LevelHeader,
+    pub blocks: Vec<BlockInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<ChannelColor>,
+}
+
+//x position where the level is completed
+pub fn level_end(level: &LevelFile) -> f32 {
+    blocks_end(level.blocks.iter())
+}
+
+//same as level_end, for objects that aren't in a level file (yet)
+pub fn blocks_end<'a>(blocks: impl Iterator<Item = &'a BlockInfo> + Clone) -> f32 {
+    if let Some(end_marker) = blocks.clone().find(|block| block.marker_type == 9) {
+        return end_marker.coords.0 as f32;
+    }
+
+    blocks
+        .map(|block| block.coords.0 as f32)
+        .fold(STARTING_PLAYER_POSTION.x, f32::max)
+        + LEVEL_END_PADDING
+}
+
+//lowest and highest y a player can be at, past them nothing can bring it back
+pub fn level_bounds_y(level: &LevelFile) -> (f32, f32) {
+    let (bottom, top) = level
+        .blocks
+        .iter()
+        .map(|block| block.coords.1 as f32)
+        .fold((STARTING_PLAYER_POSTION.y, STARTING_PLAYER_POSTION.y), |(bottom, top), y| {
+            (bottom.min(y), top.max(y))
+        });
+    (bottom - LEVEL_BOUNDS_PADDING, top + LEVEL_BOUNDS_PADDING)
+}
+
+//where the player's speed changes, starting with the player's spawn
+pub fn speed_sections(
+    starting_speed: SpeedTier,
+    portals: impl Iterator<Item = (f32, SpeedTier)>,
+) -> Vec<(f32, SpeedTier)> {
+    let mut sections: Vec<(f32, SpeedTier)> = portals.filter(|(x, _)| *x >= STARTING_PLAYER_POSTION.x).collect();
+    sections.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    sections.insert(0, (STARTING_PLAYER_POSTION.x, starting_speed));
+    sections
+}
+
+//where the player is after the given seconds of play, and how fast it moves there
+pub fn x_at_time(sections: &[(f32, SpeedTier)], time: f32) -> (f32, SpeedTier) {
+    let mut time_left = time;
+    for (i, (x, tier)) in sections.iter().enumerate() {
+        match sections.get(i + 1) {
+            Some((next_x, _)) if (next_x - x) / tier.value() < time_left => {
+                time_left -= (next_x - x) / tier.value();
+            }
+            _ => return (x + tier.value() * time_left, *tier),
+        }
+    }
+    (STARTING_PLAYER_POSTION.x, SpeedTier::default())
+}
+
+pub fn time_at_x(sections: &[(f32, SpeedTier)], target_x: f32) -> f32 {
+    let mut time = 0.0;
+    for (i, (x, tier)) in sections.iter().enumerate() {
+        let next_x = sections.get(i + 1).map(|section| section.0).unwrap_or(f32::MAX);
+        time += (next_x.min(target_x) - x).max(0.0) / tier.value();
+        if target_x <= next_x {
+            break;
+        }
+    }
+    time
+}
+
+//seconds it takes to play the level from start to end
+pub fn level_duration(level: &LevelFile) -> f32 {
+    let portals = level
+        .blocks
+        .iter()
+        .filter(|block| block.marker_type == 3)
+        .map(|block| (block.coords.0 as f32, block.speed.unwrap_or_default()));
+    time_at_x(&speed_sections(level.header.starting_speed, portals), level_end(level))
+}
+
+//problems that don't stop the level from loading but make it play wrong, found without playing it
+pub fn lint_level(level: &LevelFile) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    match level.blocks.iter().filter(|block| block.marker_type == 9).count() {
+        0 => problems.push("No level end, the level ends after the last object".to_string()),
+        1 => {}
+        count => problems.push(format!("{} level ends, only the first one counts", count)),
+    }
+
+    let mut coins: Vec<u8> = level
+        .blocks
+        .iter()
+        .filter(|block| block.marker_type == 10)
+        .map(|block| block.coin.unwrap_or(0))
+        .collect();
+    if coins.len() > MAX_COINS {
+        problems.push(format!("{} coins, a level can't have more than {}", coins.len(), MAX_COINS));
+    }
+    coins.sort();
+    let mut duplicates: Vec<u8> = coins.windows(2).filter(|pair| pair[0] == pair[1]).map(|pair| pair[0]).collect();
+    duplicates.dedup();
+    for index in duplicates {
+        problems.push(format!("More than one coin with index {}", index));
+    }
+
+    let groups: HashSet<u16> = level.blocks.iter().flat_map(|block| block.groups.iter().copied()).collect();
+    for block in level.blocks.iter().filter(|block| block.marker_type == 11) {
+        let trigger = match &block.trigger {
+            Some(trigger) => trigger,
+            None => {
+                problems.push(format!("Trigger at {:?} has no settings", block.coords));
+                continue;
+            }
+        };
+        match trigger.kind {
+            //these don't use the target group
+            TriggerKind::Color { .. }
+            | TriggerKind::CameraOffset { .. }
+            | TriggerKind::CameraZoom { .. }
+            | TriggerKind::CameraStatic { .. }
+            | TriggerKind::CameraSection { .. } => {}
+            TriggerKind::Rotate { center_group, .. } if center_group != 0 && !groups.contains(&center_group) => {
+                problems.push(format!(
+                    "Trigger at {:?} rotates around group {}, which has no objects",
+                    block.coords, center_group
+                ));
+            }
+            _ if !groups.contains(&trigger.target_group) => {
+                problems.push(format!(
+                    "Trigger at {:?} targets group {}, which has no objects",
+                    block.coords, trigger.target_group
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for block in level.blocks.iter() {
+        if !seen.insert((block.id, block.marker_type, block.coords)) {
+            problems.push(format!("{} at {:?} is placed more than once", block.name, block.coords));
+        }
+    }
+
+    if let Some(music) = level.header.music.as_ref().filter(|music| !music.path.is_empty()) {
+        if !Path::new(ASSETS_DIRECTORY).join(&music.path).is_file() {
+            problems.push(format!("Music file {} not found in {}", music.path, ASSETS_DIRECTORY));
+        }
+    }
+
+    problems
+}
+
+//identifies a level by its content, so stats follow the level even if the file is renamed
+pub fn level_hash(path: &str) -> String {
+    //FNV-1a, stable across builds unlike the std hasher
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in std::fs::read(path).unwrap_or_default() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+//levels saved before the header existed are a bare list of blocks
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyLevelFile {
+    Blocks(Vec<BlockInfo>),
+    Level(LevelFile),
+}
+
+impl From<AnyLevelFile> for LevelFile {
+    fn from(level: AnyLevelFile) -> Self {
+        match level {
+            AnyLevelFile::Blocks(blocks) => LevelFile { blocks, ..default() },
+            AnyLevelFile::Level(level) => level,
+        }
+    }
+}
+
+pub fn load_level(path: &str) -> LevelFile {
+    let file = File::open(path).unwrap();
+    let reader = BufReader::new(file);
+    serde_json::from_reader::<_, AnyLevelFile>(reader).unwrap().into()
+}
+
+//for listing levels, where one broken file shouldn't take the game down
+pub fn try_load_level(path: &str) -> Option<LevelFile> {
+    let file = File::open(path).ok()?;
+    serde_json::from_reader::<_, AnyLevelFile>(BufReader::new(file)).ok().map(LevelFile::from)
+}
+
+pub fn save_level(path: &str, level: &LevelFile) {
+    let file = File::create(path).unwrap();
+    serde_json::to_writer_pretty(file, level).unwrap();
+}
+
+//the color of objects drawn without a texture
+pub fn portal_color(block_info: &BlockInfo) -> Color {
+    match block_info.marker_type {
+        7 | 8 => block_info.pad.unwrap_or_default().color(),
+        9 => Color::WHITE,
+        10 => Color::GOLD,
+        11 => match block_info.trigger.as_ref().map(|trigger| &trigger.kind) {
+            Some(TriggerKind::Rotate { .. }) => Color::VIOLET,
+            Some(TriggerKind::Alpha { .. }) => Color::SILVER,
+            Some(TriggerKind::Toggle { .. }) => Color::MAROON,
+            Some(TriggerKind::Color { .. }) => Color::SALMON,
+            Some(TriggerKind::CameraOffset { .. })
+            | Some(TriggerKind::CameraZoom { .. })
+            | Some(TriggerKind::CameraStatic { .. })
+            | Some(TriggerKind::CameraSection { .. }) => Color::AQUAMARINE,
+            _ => Color::PURPLE,
+        },
+        3 => block_info.speed.unwrap_or_default().color(),
+        4 => block_info.size.unwrap_or_default().color(),
+        5 => {
+            if block_info.mirrored.unwrap_or(false) {
+                Color::ORANGE_RED
+            } else {
+                Color::BLUE
+            }
+        }
+        _ => {
+            if block_info.dual.unwrap_or(false) {
+                Color::GOLD
+            } else {
+                Color::TEAL
+            }
+        }
+    }
+}
+
+pub fn spawn_block(commands: &mut Commands, game_assets: &GameAssets, block_info: &BlockInfo) -> Entity {
+    let entity = spawn_block_entity(commands, game_assets, block_info);
+    if !block_info.groups.is_empty() {
+        commands.entity(entity).insert(GroupMember::new(block_info.groups.clone()));
+    }
+    if let Some(channel) = block_info.color_channel {
+        commands.entity(entity).insert(ColorChannel(channel));
+    }
+    entity
+}
+
+//the sprite color an object has when no color channel is applied
+pub fn base_color(block_info: &BlockInfo) -> Color {
+    match block_info.marker_type {
+        0..=2 => Color::WHITE,
+        _ => portal_color(block_info),
+    }
+}
+
+fn spawn_block_entity(commands: &mut Commands, game_assets: &GameAssets, block_info: &BlockInfo) -> Entity {
+    let translation = Vec3::new(block_info.coords.0 as f32, block_info.coords.1 as f32, 0.0);
+
+    match block_info.marker_type {
+        3..=6 => {
+            let mut entity = commands.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: portal_color(block_info),
+                    custom_size: Some(Vec2::new(16.0, 48.0)),
+                    ..default()
+                },
+                ..default()
+            });
+            entity
+                .insert(Transform {
+                    translation,
+                    scale: Vec3::new(2.0, 2.0, 1.0),
+                    ..default()
+                })
+                .insert(Sensor)
+                .insert(Collider::cuboid(8.0, 24.0))
+                .insert(LevelObject {
+                    info: block_info.clone(),
+                })
+                .insert(Name::new(block_info.name.clone()));
+
+            match block_info.marker_type {
+                3 => {
+                    entity.insert(SpeedPortal {
+                        tier: block_info.speed.unwrap_or_default(),
+                    });
+                }
+                4 => {
+                    entity.insert(SizePortal {
+                        size: block_info.size.unwrap_or_default(),
+                    });
+                }
+                5 => {
+                    entity.insert(MirrorPortal {
+                        mirrored: block_info.mirrored.unwrap_or(false),
+                    });
+                }
+                _ => {
+                    entity.insert(DualPortal {
+                        dual: block_info.dual.unwrap_or(false),
+                    });
+                }
+            }
+
+            entity.id()
+        }
+        7 | 8 => {
+            let color = block_info.pad.unwrap_or_default();
+            let size = if block_info.marker_type == 7 {
+                Vec2::new(28.0, 6.0)
+            } else {
+                Vec2::new(24.0, 24.0)
+            };
+
+            let mut entity = commands.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: color.color(),
+                    custom_size: Some(size),
+                    ..default()
+                },
+                ..default()
+            });
+            entity
+                .insert(Transform {
+                    translation,
+                    scale: Vec3::new(2.0, 2.0, 1.0),
+                    ..default()
+                })
+                .insert(Sensor)
+                .insert(Collider::cuboid(size.x / 2.0, size.y / 2.0))
+                .insert(LevelObject {
+                    info: block_info.clone(),
+                })
+                .insert(Name::new(block_info.name.clone()));
+
+            if block_info.marker_type == 7 {
+                entity.insert(JumpPad { color });
+            } else {
+                entity.insert(JumpOrb { color });
+            }
+            entity.insert(UsedBy::default());
+
+            entity.id()
+        }
+        9 => commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: portal_color(block_info),
+                    custom_size: Some(Vec2::new(4.0, 1000.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                ..default()
+            })
+            .insert(LevelEndMarker)
+            .insert(LevelObject {
+                info: block_info.clone(),
+            })
+            .insert(Name::new(block_info.name.clone()))
+            .id(),
+        10 => commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: portal_color(block_info),
+                    custom_size: Some(Vec2::new(20.0, 20.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Transform {
+                translation,
+                scale: Vec3::new(2.0, 2.0, 1.0),
+                ..default()
+            })
+            .insert(Sensor)
+            .insert(Collider::cuboid(10.0, 10.0))
+            .insert(Coin {
+                index: block_info.coin.unwrap_or(0),
+            })
+            .insert(Activated(false))
+            .insert(LevelObject {
+                info: block_info.clone(),
+            })
+            .insert(Name::new(block_info.name.clone()))
+            .id(),
+        11 => commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: portal_color(block_info),
+                    custom_size: Some(Vec2::new(24.0, 24.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                ..default()
+            })
+            .insert(Trigger {
+                info: block_info.trigger.clone().unwrap_or_else(TriggerInfo::default_move),
+            })
+            .insert(Activated(false))
+            .insert(LevelObject {
+                info: block_info.clone(),
+            })
+            .insert(Name::new(block_info.name.clone()))
+            .id(),
+        _ => {
+            let mut entity = commands.spawn(SpriteBundle {
+                texture: game_assets.blocks[block_info.id as usize].clone(),
+                ..default()
+            });
+            entity
+                .insert(Transform {
+                    translation,
+                    scale: Vec3::new(2.0, 2.0, 1.0),
+                    ..default()
+                })
+                .insert(LevelObject {
+                    info: block_info.clone(),
+                })
+                .insert(Name::new(block_info.name.clone()));
+
+            match block_info.marker_type {
+                0 => {
+                    entity
+                        .insert(BlockType::Spike(SpikeMarker))
+                        .insert(SpikeMarker)
+                        .insert(Collider::cuboid(4.0, 10.0));
+                }
+                1 => {
+                    entity
+                        .insert(BlockType::Ground(GroundMarker))
+                        .insert(GroundMarker)
+                        .insert(RigidBody::Fixed)
+                        .insert(Collider::cuboid(15.0, 15.0));
+                }
+                _ => {
+                    entity
+                        .insert(BlockType::Clipped(ClippedBlockMarker))
+                        .insert(ClippedBlockMarker)
+                        .insert(Collider::cuboid(4.0, 10.0));
+                }
+            }
+
+            entity.id()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(blocks: Vec<BlockInfo>) -> LevelFile {
+        LevelFile { blocks, ..default() }
+    }
+
+    fn level_end_at(x: i32) -> BlockInfo {
+        BlockInfo::new(0, "LevelEnd", 9, (x, 0))
+    }
+
+    fn coin(index: u8, x: i32) -> BlockInfo {
+        BlockInfo {
+            coin: Some(index),
+            ..BlockInfo::new(0, "Coin", 10, (x, 64))
+        }
+    }
+
+    fn trigger(kind: TriggerKind, target_group: u16, x: i32) -> BlockInfo {
+        BlockInfo {
+            name: format!("{}Trigger", kind.name()),
+            trigger: Some(TriggerInfo {
+                target_group,
+                ..TriggerInfo::new(kind)
+            }),
+            ..BlockInfo::new(0, "", 11, (x, 0))
+        }
+    }
+
+    fn grouped_block(group: u16, x: i32) -> BlockInfo {
+        BlockInfo {
+            groups: vec![group],
+            ..BlockInfo::new(0, "Block0", 1, (x, 0))
+        }
+    }
+
+    #[test]
+    fn old_levels_are_a_bare_list_of_blocks() {
+        let json = r#"[{"id": 0, "name": "Spike0", "marker_type": 0, "coords": [0, -256]}]"#;
+        let level: LevelFile = serde_json::from_str::<AnyLevelFile>(json).unwrap().into();
+
+        assert_eq!(level.blocks.len(), 1);
+        assert_eq!(level.header.starting_speed, SpeedTier::default());
+    }
+
+    #[test]
+    fn clean_level_has_no_problems() {
+        let level = level(vec![
+            grouped_block(1, 0),
+            trigger(TriggerInfo::default_move().kind, 1, -64),
+            coin(0, 64),
+            coin(1, 128),
+            level_end_at(640),
+        ]);
+
+        assert!(lint_level(&level).is_empty(), "{:?}", lint_level(&level));
+    }
+
+    #[test]
+    fn duplicate_coin_indices() {
+        let level = level(vec![coin(1, 64), coin(1, 128), coin(1, 192), level_end_at(640)]);
+
+        assert_eq!(lint_level(&level), vec!["More than one coin with index 1".to_string()]);
+    }
+
+    #[test]
+    fn missing_level_end() {
+        let level = level(vec![BlockInfo::new(0, "Block0", 1, (0, 0))]);
+
+        assert_eq!(
+            lint_level(&level),
+            vec!["No level end, the level ends after the last object".to_string()]
+        );
+    }
+
+    #[test]
+    fn more_than_one_level_end() {
+        let level = level(vec![level_end_at(640), level_end_at(1280)]);
+
+        assert_eq!(lint_level(&level), vec!["2 level ends, only the first one counts".to_string()]);
+    }
+
+    #[test]
+    fn trigger_targeting_unknown_group() {
+        let level = level(vec![
+            grouped_block(1, 0),
+            trigger(TriggerInfo::default_move().kind, 2, -64),
+            level_end_at(640),
+        ]);
+
+        assert_eq!(
+            lint_level(&level),
+            vec!["Trigger at (-64, 0) targets group 2, which has no objects".to_string()]
+        );
+    }
+
+    #[test]
+    fn rotate_around_unknown_center_group() {
+        let kind = TriggerKind::Rotate {
+            degrees: 90.0,
+            center_group: 3,
+        };
+        let level = level(vec![grouped_block(1, 0), trigger(kind, 1, -64), level_end_at(640)]);
+
+        assert_eq!(
+            lint_level(&level),
+            vec!["Trigger at (-64, 0) rotates around group 3, which has no objects".to_string()]
+        );
+    }
+
+    #[test]
+    fn triggers_without_a_target_are_not_flagged() {
+        let level = level(vec![
+            trigger(TriggerKind::CameraZoom { zoom: 1.0 }, 5, -64),
+            trigger(
+                TriggerKind::Color {
+                    channel: 1,
+                    color: [0.0, 0.0, 0.0],
+                },
+                5,
+                -128,
+            ),
+            level_end_at(640),
+        ]);
+
+        assert!(lint_level(&level).is_empty(), "{:?}", lint_level(&level));
+    }
+}
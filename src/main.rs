@@ -1,14 +1,72 @@
 use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_rapier2d::prelude::*;
 use std::env;
 
 mod player;
-use player::{LevelState, PlayerPlugin};
+use player::{LevelState, PlayerPlugin, SpeedTier};
 
 mod editor;
 use editor::{EditorPlugin, EditorState};
 
+mod level;
+use level::{LevelHeader, DEFAULT_LEVEL_PATH};
+
+mod portal;
+use portal::PortalPlugin;
+
+mod pad;
+use pad::PadPlugin;
+
+mod results;
+use results::ResultsPlugin;
+
+mod progress;
+use progress::ProgressPlugin;
+
+mod practice;
+use practice::PracticePlugin;
+
+mod coin;
+use coin::CoinPlugin;
+
+mod trigger;
+use trigger::TriggerPlugin;
+
+mod color;
+use color::{ColorChannel, ColorPlugin, GROUND_CHANNEL, LINE_CHANNEL};
+
+mod music;
+use music::MusicPlugin;
+
+mod sound;
+use sound::{SoundPlugin, SoundSettings};
+
+mod profile;
+use profile::ProfilePlugin;
+
+mod menu;
+use menu::MenuPlugin;
+
+mod pause;
+use pause::PausePlugin;
+
+mod input;
+use input::InputActionPlugin;
+
+mod settings;
+use settings::{load_settings, SettingsPlugin};
+
+mod fps;
+use fps::FpsPlugin;
+
+mod hitbox;
+use hitbox::HitboxPlugin;
+
+mod console;
+use console::ConsolePlugin;
+
 //markers
 #[derive(Component)]
 struct GroundMarker;
@@ -17,6 +75,7 @@ struct GroundMarker;
 const BG_COLOR: Color = Color::rgb(0.2, 0.36, 0.89);
 pub const HEIGHT: f32 = 720.0;
 pub const WIDTH: f32 = 1280.0;
+pub const VIEW_HEIGHT: f32 = HEIGHT; //world units the gameplay cameras show vertically
 
 #[derive(Resource)]
 struct GameAssets {
@@ -29,16 +88,19 @@ struct GameAssets {
 #[derive(Resource)]
 pub struct GameState {
     variant: GameStateVariant,
+    level_path: String, //the level that is played or edited
 }
 
 static mut GAME_STATE_EARLY: i32 = 0; //setting up the game state early via
                                       //cla and then changing the actual GameState based on this value
 
+//0- menu
 //1- game
 //2- editor
 
 #[derive(Eq, PartialEq)]
 pub enum GameStateVariant {
+    Menu,   //main menu and level select
     Editor, //you can move the camera, the player does not exist
     Level,  //you can control the player but no the camera
 }
@@ -59,45 +121,63 @@ fn main() {
         }
         Some(input) => {
             println!(
-                "You entered '{}', but I was expecting either 'game' or 'editor'.",
+                "You entered '{}', but I was expecting either 'game' or 'editor', opening the menu.",
                 input
             );
         }
-        None => {
-            println!("Please provide an argument.");
-        }
+        None => {}
     }
 
+    let muted = env::args().any(|arg| arg == "--mute");
+    let settings = load_settings();
+
     App::new()
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
-                    window: WindowDescriptor {
-                        width: WIDTH,
-                        height: HEIGHT,
-                        title: "Bevy Tower Defense".to_string(),
-                        resizable: false,
-                        ..Default::default()
-                    },
+                    window: settings.window_descriptor(),
                     ..default()
                 })
                 .set(ImagePlugin::default_nearest()),
         )
         .add_plugin(WorldInspectorPlugin)
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(1.0))
-        .add_plugin(RapierDebugRenderPlugin {
-            mode: DebugRenderMode::empty(),
-            ..default()
-        })
         .add_plugin(PlayerPlugin)
         .add_plugin(EditorPlugin)
+        .add_plugin(PortalPlugin)
+        .add_plugin(PadPlugin)
+        .add_plugin(ResultsPlugin)
+        .add_plugin(ProgressPlugin)
+        .add_plugin(PracticePlugin)
+        .add_plugin(CoinPlugin)
+        .add_plugin(TriggerPlugin)
+        .add_plugin(ColorPlugin)
+        .add_plugin(MusicPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(ProfilePlugin)
+        .add_plugin(MenuPlugin)
+        .add_plugin(PausePlugin)
+        .add_plugin(InputActionPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(FpsPlugin)
+        .add_plugin(HitboxPlugin)
+        .add_plugin(ConsolePlugin)
         .insert_resource(ClearColor(BG_COLOR))
+        .insert_resource(SoundSettings { muted })
+        .insert_resource(settings)
         .add_startup_system_to_stage(StartupStage::PreStartup, asset_loading)
         .add_startup_system(setup)
-        .add_system(bevy::window::close_on_esc)
         .run();
 }
 
+//any window size shows the same height of the level, wider windows see further ahead
+pub fn game_camera(translation: Vec3) -> Camera2dBundle {
+    let mut camera = Camera2dBundle::default();
+    camera.transform.translation = translation;
+    camera.projection.scaling_mode = ScalingMode::FixedVertical(VIEW_HEIGHT);
+    camera
+}
+
 fn asset_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
     let cube0 = asset_server.load("cube0.png");
     let cube1 = asset_server.load("cube1.png");
@@ -149,24 +229,66 @@ fn setup(mut commands: Commands, game_assets: Res<GameAssets>) {
         .insert(RigidBody::Fixed)
         .insert(Collider::cuboid(150.0, 15.0))
         .insert(GroundMarker)
-        .insert(Name::new("Ground"));
+        .insert(ColorChannel(GROUND_CHANNEL))
+        .insert(Name::new("Ground"))
+        .with_children(|parent| {
+            //thin line along the top edge of the ground
+            parent
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(32.0, 1.0)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 16.0, 0.1),
+                    ..default()
+                })
+                .insert(ColorChannel(LINE_CHANNEL))
+                .insert(Name::new("GroundLine"));
+        });
 
     commands.insert_resource(GameState {
         variant: unsafe {
             match GAME_STATE_EARLY {
                 1 => GameStateVariant::Level,
-                _ => GameStateVariant::Editor,
+                2 => GameStateVariant::Editor,
+                _ => GameStateVariant::Menu,
             }
         },
+        level_path: DEFAULT_LEVEL_PATH.to_string(),
     });
 
     commands.insert_resource(EditorState {
         active: false,
         picked_block_id: 0,
         freeze_block_placing: false,
+        header: LevelHeader::default(),
+        selected: None,
+        groups_text: String::new(),
+        picked_channel: 1,
+        status: String::new(),
+        saved_contents: Vec::new(),
     });
     commands.insert_resource(LevelState {
         attempts: 0,
         active: false,
+        starting_speed: SpeedTier::Normal,
+        mirrored: false,
+        end_x: 0.0,
+        bounds_y: (0.0, 0.0),
+        completed: false,
+        attempt_time: 0.0,
+        level_time: 0.0,
+        jumps: 0,
+        practice: false,
+        start_snapshot: None,
+        death_timer: None,
+        death_position: None,
+        music: None,
+        level_key: String::new(),
+        paused: false,
+        noclip: false,
+        cheated: false,
+        spawned: false,
+        reopen: false,
     });
 }
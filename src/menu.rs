@@ -0,0 +1,307 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::level::{
+    level_duration, level_hash, save_level, try_load_level, Difficulty, LevelFile, LevelHeader, LEVELS_DIRECTORY,
+};
+use crate::profile::Profile;
+use crate::settings::OpenSettingsEvent;
+use crate::{GameAssets, GameState, GameStateVariant};
+
+#[derive(Component)]
+struct MenuRootMarker;
+#[derive(Component)]
+struct MenuCameraMarker;
+
+#[derive(Component)]
+enum MenuButton {
+    Play,
+    Create,
+    Settings,
+    Quit,
+    Back,
+    NewLevel,
+    Open(String), //path of the level
+}
+
+#[derive(PartialEq, Eq)]
+enum MenuScreen {
+    Main,
+    LevelSelect,
+}
+
+#[derive(Resource)]
+struct MenuState {
+    screen: MenuScreen,
+    editing: bool, //whether the level select opens levels in the editor
+}
+
+struct LevelEntry {
+    path: String,
+    name: String,
+    author: String,
+    difficulty: Difficulty,
+    seconds: f32,
+    best: f32,
+    coins: usize,
+    total_coins: usize,
+}
+
+fn length_name(seconds: f32) -> &'static str {
+    match seconds {
+        s if s < 10.0 => "Tiny",
+        s if s < 30.0 => "Short",
+        s if s < 60.0 => "Medium",
+        s if s < 120.0 => "Long",
+        _ => "XL",
+    }
+}
+
+//every level file in the levels directory, files that can't be read are skipped
+fn scan_levels(profile: &Profile) -> Vec<LevelEntry> {
+    let mut paths: Vec<String> = fs::read_dir(LEVELS_DIRECTORY)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |extension| extension == "json"))
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let level = try_load_level(&path)?;
+            let stats = profile.stats(&level_hash(&path));
+            let name = if level.header.name.is_empty() {
+                Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            } else {
+                level.header.name.clone()
+            };
+
+            Some(LevelEntry {
+                name,
+                author: level.header.author.clone(),
+                difficulty: level.header.difficulty,
+                seconds: level_duration(&level),
+                best: stats.best_normal,
+                coins: stats.coins.iter().filter(|collected| **collected).count(),
+                total_coins: level.blocks.iter().filter(|block| block.marker_type == 10).count(),
+                path,
+            })
+        })
+        .collect()
+}
+
+//an empty level with a name that isn't taken yet
+fn create_level() -> String {
+    let _ = fs::create_dir_all(LEVELS_DIRECTORY);
+    let mut number = 1;
+    while Path::new(&format!("{}/level_{}.json", LEVELS_DIRECTORY, number)).exists() {
+        number += 1;
+    }
+
+    let path = format!("{}/level_{}.json", LEVELS_DIRECTORY, number);
+    let level = LevelFile {
+        header: LevelHeader {
+            name: format!("Level {}", number),
+            ..default()
+        },
+        ..default()
+    };
+    save_level(&path, &level);
+    path
+}
+
+fn spawn_button(commands: &mut ChildBuilder, text_style: &TextStyle, button: MenuButton, label: &str, width: f32) {
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(width), Val::Px(50.0)),
+                margin: UiRect::all(Val::Px(8.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::DARK_GRAY.into(),
+            ..default()
+        })
+        .insert(button)
+        .insert(Name::new(label.to_string()))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(label, text_style.clone()));
+        });
+}
+
+fn spawn_menu(
+    mut commands: Commands,
+    root_query: Query<Entity, With<MenuRootMarker>>,
+    camera_query: Query<Entity, With<MenuCameraMarker>>,
+    game_state: Res<GameState>,
+    menu_state: Res<MenuState>,
+    profile: Res<Profile>,
+    game_assets: Res<GameAssets>,
+) {
+    if game_state.variant != GameStateVariant::Menu {
+        for entity in root_query.iter().chain(camera_query.iter()) {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    if camera_query.is_empty() {
+        commands.spawn((Camera2dBundle::default(), MenuCameraMarker, Name::new("MenuCamera")));
+    }
+    if !root_query.is_empty() && !menu_state.is_changed() {
+        return;
+    }
+    for entity in root_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let title_style = TextStyle {
+        font: game_assets.font_roboto_black.clone(),
+        font_size: 60.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: game_assets.font_roboto_black.clone(),
+        font_size: 28.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+            ..default()
+        })
+        .insert(MenuRootMarker)
+        .insert(Name::new("Menu"))
+        .with_children(|commands| match menu_state.screen {
+            MenuScreen::Main => {
+                commands.spawn(TextBundle::from_section("Geometry Dash", title_style.clone()));
+                spawn_button(commands, &text_style, MenuButton::Play, "Play", 260.0);
+                spawn_button(commands, &text_style, MenuButton::Create, "Create", 260.0);
+                spawn_button(commands, &text_style, MenuButton::Settings, "Settings", 260.0);
+                spawn_button(commands, &text_style, MenuButton::Quit, "Quit", 260.0);
+            }
+            MenuScreen::LevelSelect => {
+                let title = if menu_state.editing { "Edit a level" } else { "Select a level" };
+                commands.spawn(TextBundle::from_section(title, title_style.clone()));
+
+                let levels = scan_levels(&profile);
+                if levels.is_empty() {
+                    commands.spawn(TextBundle::from_section(
+                        format!("No levels in the '{}' folder", LEVELS_DIRECTORY),
+                        text_style.clone(),
+                    ));
+                }
+
+                for level in levels {
+                    let by = if level.author.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" by {}", level.author)
+                    };
+                    let info = format!(
+                        "{}{} | {:?} | {} ({:.0}s) | best {}% | coins {}/{}",
+                        level.name,
+                        by,
+                        level.difficulty,
+                        length_name(level.seconds),
+                        level.seconds,
+                        level.best as u32,
+                        level.coins,
+                        level.total_coins,
+                    );
+                    let label = if menu_state.editing { "Edit" } else { "Play" };
+
+                    commands
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|commands| {
+                            commands.spawn(TextBundle::from_section(info, text_style.clone()));
+                            spawn_button(commands, &text_style, MenuButton::Open(level.path), label, 120.0);
+                        });
+                }
+
+                commands
+                    .spawn(NodeBundle::default())
+                    .with_children(|commands| {
+                        if menu_state.editing {
+                            spawn_button(commands, &text_style, MenuButton::NewLevel, "New level", 200.0);
+                        }
+                        spawn_button(commands, &text_style, MenuButton::Back, "Back", 200.0);
+                    });
+            }
+        });
+}
+
+fn menu_button_clicked(
+    interaction: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut menu_state: ResMut<MenuState>,
+    mut game_state: ResMut<GameState>,
+    mut app_exit_ev: EventWriter<AppExit>,
+    mut open_settings_ev: EventWriter<OpenSettingsEvent>,
+) {
+    for (interaction, button) in &interaction {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+
+        match button {
+            MenuButton::Play | MenuButton::Create => {
+                menu_state.screen = MenuScreen::LevelSelect;
+                menu_state.editing = matches!(button, MenuButton::Create);
+            }
+            MenuButton::Settings => open_settings_ev.send_default(),
+            MenuButton::Quit => app_exit_ev.send(AppExit),
+            MenuButton::Back => menu_state.screen = MenuScreen::Main,
+            MenuButton::NewLevel => {
+                game_state.level_path = create_level();
+                game_state.variant = GameStateVariant::Editor;
+            }
+            MenuButton::Open(path) => {
+                game_state.level_path = path.clone();
+                game_state.variant = if menu_state.editing {
+                    GameStateVariant::Editor
+                } else {
+                    GameStateVariant::Level
+                };
+            }
+        }
+    }
+}
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MenuState {
+            screen: MenuScreen::Main,
+            editing: false,
+        })
+        .add_system(spawn_menu)
+        .add_system(menu_button_clicked.before(spawn_menu));
+    }
+}
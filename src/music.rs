@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use bevy::audio::{play_queued_audio_system, AudioOutput, AudioSink, Source};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+
+use crate::player::{LevelState, RespawnPlayerEvent};
+use crate::practice::Checkpoints;
+use crate::settings::{PracticeMusic, Settings};
+use crate::sound::SoundSettings;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LevelMusic {
+    pub path: String, //relative to the assets folder
+    #[serde(default)]
+    pub offset: f32, //seconds of the song skipped before the level starts
+}
+
+//an audio file that starts playing somewhere in the middle, bevy can't seek a playing sink
+#[derive(TypeUuid)]
+#[uuid = "4b3c8f0e-6a57-4d9c-9a0f-2f6a1d7e5c31"]
+pub struct MusicClip {
+    source: AudioSource,
+    start: f32,
+}
+
+impl Decodable for MusicClip {
+    type Decoder = Box<dyn Source<Item = i16> + Send>;
+    type DecoderItem = i16;
+
+    fn decoder(&self) -> Self::Decoder {
+        Box::new(self.source.decoder().skip_duration(Duration::from_secs_f32(self.start.max(0.0))))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct MusicPlayer {
+    source: Option<Handle<AudioSource>>,
+    offset: f32,
+    pending: Option<f32>, //level position to start from once the file is loaded
+    clip: Option<Handle<MusicClip>>,
+    sink: Option<Handle<AudioSink>>,
+    started_at: f64, //time the level position 0 would have been played at
+}
+
+impl MusicPlayer {
+    pub fn set_music(&mut self, music: Option<&LevelMusic>, asset_server: &AssetServer) {
+        self.source = music
+            .filter(|music| !music.path.is_empty())
+            .map(|music| asset_server.load(music.path.as_str()));
+        self.offset = music.map(|music| music.offset).unwrap_or(0.0);
+    }
+
+    pub fn play_from(&mut self, position: f32) {
+        self.pending = Some(position);
+    }
+
+    pub fn stop(&mut self, sinks: &Assets<AudioSink>) {
+        self.pending = None;
+        self.stop_sink(sinks);
+    }
+
+    fn stop_sink(&mut self, sinks: &Assets<AudioSink>) {
+        if let Some(sink) = self.sink.take().and_then(|sink| sinks.get(&sink)) {
+            sink.stop();
+        }
+        self.clip = None;
+    }
+
+    pub fn set_paused(&self, paused: bool, sinks: &Assets<AudioSink>) {
+        if let Some(sink) = self.sink.as_ref().and_then(|sink| sinks.get(sink)) {
+            if paused {
+                sink.pause();
+            } else {
+                sink.play();
+            }
+        }
+    }
+
+    pub fn set_volume(&self, volume: f32, sinks: &Assets<AudioSink>) {
+        if let Some(sink) = self.sink.as_ref().and_then(|sink| sinks.get(sink)) {
+            sink.set_volume(volume);
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    //seconds into the level the song is at right now
+    pub fn position(&self, time: &Time) -> f32 {
+        (time.elapsed_seconds_f64() - self.started_at) as f32
+    }
+}
+
+fn play_pending_music(
+    audio: Res<Audio<MusicClip>>,
+    sources: Res<Assets<AudioSource>>,
+    mut clips: ResMut<Assets<MusicClip>>,
+    sinks: Res<Assets<AudioSink>>,
+    time: Res<Time>,
+    level_state: Res<LevelState>,
+    settings: Res<Settings>,
+    sound_settings: Res<SoundSettings>,
+    mut player: ResMut<MusicPlayer>,
+) {
+    //the song waits for the player to respawn
+    if level_state.death_timer.is_some() {
+        return;
+    }
+    let position = match player.pending {
+        Some(position) => position,
+        None => return,
+    };
+    let source = match player.source.as_ref() {
+        Some(handle) => match sources.get(handle) {
+            Some(source) => source.clone(),
+            None => return, //still loading
+        },
+        None => {
+            player.pending = None;
+            return;
+        }
+    };
+
+    player.stop_sink(&sinks);
+    let clip = clips.add(MusicClip {
+        source,
+        start: player.offset + position,
+    });
+    let sink = audio.play_with_settings(
+        clip.clone(),
+        PlaybackSettings::ONCE.with_volume(music_volume(&settings, &sound_settings)),
+    );
+    player.sink = Some(sinks.get_handle(sink));
+    player.clip = Some(clip);
+    player.pending = None;
+    player.started_at = time.elapsed_seconds_f64() - position as f64;
+}
+
+fn music_volume(settings: &Settings, sound_settings: &SoundSettings) -> f32 {
+    if sound_settings.muted {
+        0.0
+    } else {
+        settings.music_volume
+    }
+}
+
+fn apply_music_volume(
+    sinks: Res<Assets<AudioSink>>,
+    settings: Res<Settings>,
+    sound_settings: Res<SoundSettings>,
+    player: Res<MusicPlayer>,
+) {
+    if settings.is_changed() {
+        player.set_volume(music_volume(&settings, &sound_settings), &sinks);
+    }
+}
+
+fn level_music(
+    asset_server: Res<AssetServer>,
+    sinks: Res<Assets<AudioSink>>,
+    level_state: Res<LevelState>,
+    mut player: ResMut<MusicPlayer>,
+    mut playing_level: Local<bool>,
+) {
+    if level_state.active && !*playing_level {
+        player.set_music(level_state.music.as_ref(), &asset_server);
+        player.play_from(0.0);
+    } else if !level_state.active && *playing_level {
+        player.stop(&sinks);
+    }
+    *playing_level = level_state.active;
+}
+
+fn stop_music_on_death(sinks: Res<Assets<AudioSink>>, level_state: Res<LevelState>, mut player: ResMut<MusicPlayer>) {
+    if level_state.active && level_state.death_timer.is_some() && player.is_playing() {
+        player.stop_sink(&sinks);
+    }
+}
+
+//every attempt restarts the song, practice attempts pick it up at the checkpoint unless it's turned off
+fn restart_music(
+    mut respawn_player_ev: EventReader<RespawnPlayerEvent>,
+    sinks: Res<Assets<AudioSink>>,
+    level_state: Res<LevelState>,
+    checkpoints: Res<Checkpoints>,
+    settings: Res<Settings>,
+    mut player: ResMut<MusicPlayer>,
+) {
+    if respawn_player_ev.iter().last().is_none() || !level_state.active {
+        return;
+    }
+    if level_state.practice && settings.practice_music == PracticeMusic::Off {
+        player.stop(&sinks);
+        return;
+    }
+
+    let position = if level_state.practice {
+        checkpoints.last().map(|snapshot| snapshot.time).unwrap_or(0.0)
+    } else {
+        0.0
+    };
+    player.play_from(position);
+}
+
+pub struct MusicPlugin;
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_non_send_resource::<AudioOutput<MusicClip>>()
+            .add_asset::<MusicClip>()
+            .init_resource::<Audio<MusicClip>>()
+            .add_system_to_stage(CoreStage::PostUpdate, play_queued_audio_system::<MusicClip>)
+            .init_resource::<MusicPlayer>()
+            .add_system(level_music)
+            .add_system(stop_music_on_death)
+            .add_system(apply_music_volume)
+            .add_system(restart_music)
+            .add_system(play_pending_music.after(restart_music).after(level_music));
+    }
+}
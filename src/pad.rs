@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::input::{InputAction, InputBuffer};
+use crate::player::{player_movement_jump, Jump, LevelState, PlayerMarker};
+use crate::sound::SoundEvent;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PadColor {
+    #[default]
+    Yellow,
+    Pink,
+    Red,
+    Blue, //flips gravity
+}
+
+impl PadColor {
+    pub const ALL: [PadColor; 4] = [PadColor::Yellow, PadColor::Pink, PadColor::Red, PadColor::Blue];
+
+    //multiplier of the player's jump value
+    fn pad_strength(&self) -> f32 {
+        match self {
+            PadColor::Yellow => 1.4,
+            PadColor::Pink => 0.9,
+            PadColor::Red => 1.8,
+            PadColor::Blue => 0.5,
+        }
+    }
+
+    fn orb_strength(&self) -> f32 {
+        match self {
+            PadColor::Yellow => 1.0,
+            PadColor::Pink => 0.7,
+            PadColor::Red => 1.35,
+            PadColor::Blue => 0.5,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PadColor::Yellow => Color::YELLOW,
+            PadColor::Pink => Color::PINK,
+            PadColor::Red => Color::RED,
+            PadColor::Blue => Color::CYAN,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct JumpPad {
+    pub color: PadColor,
+}
+#[derive(Component)]
+pub struct JumpOrb {
+    pub color: PadColor,
+}
+
+//players that already used the pad or orb this attempt, in dual mode each player gets its own bounce
+#[derive(Component, Default)]
+pub struct UsedBy(pub HashSet<Entity>);
+
+fn bounce(color: PadColor, strength: f32, jump: &mut Jump, velocity: &mut Velocity, gravity: &mut GravityScale) {
+    if color == PadColor::Blue {
+        gravity.0 = -gravity.0;
+        //pushes the player towards the new ground
+        velocity.linvel.y = -jump.value * strength * gravity.0.signum();
+    } else {
+        velocity.linvel.y = jump.value * strength * gravity.0.signum();
+    }
+    jump.is_jumping = true;
+}
+
+fn jump_pad_collision(
+    mut player_query: Query<(Entity, &mut Jump, &mut Velocity, &mut GravityScale), With<PlayerMarker>>,
+    mut pad_query: Query<(Entity, &JumpPad, &mut UsedBy)>,
+    rapier_context: Res<RapierContext>,
+    level_state: Res<LevelState>,
+) {
+    if level_state.active && level_state.death_timer.is_none() {
+        for (player_id, mut jump, mut velocity, mut gravity) in player_query.iter_mut() {
+            for (pad_id, pad, mut used_by) in pad_query.iter_mut() {
+                let touching = rapier_context.intersection_pair(player_id, pad_id) == Some(true);
+                if touching && !used_by.0.contains(&player_id) {
+                    bounce(pad.color, pad.color.pad_strength(), &mut jump, &mut velocity, &mut gravity);
+                    used_by.0.insert(player_id);
+                }
+            }
+        }
+    }
+}
+
+//orbs only react to a fresh press, holding the button through one does nothing
+fn jump_orb_collision(
+    mut player_query: Query<(Entity, &mut Jump, &mut Velocity, &mut GravityScale), With<PlayerMarker>>,
+    mut orb_query: Query<(Entity, &JumpOrb, &mut UsedBy)>,
+    rapier_context: Res<RapierContext>,
+    level_state: Res<LevelState>,
+    time: Res<Time>,
+    mut input_buffer: ResMut<InputBuffer>,
+    mut sound_ev: EventWriter<SoundEvent>,
+) {
+    if !level_state.active || level_state.paused || level_state.death_timer.is_some() {
+        return;
+    }
+
+    //one press can bounce every player touching an orb at the same time
+    let mut pressed = None;
+    for (player_id, mut jump, mut velocity, mut gravity) in player_query.iter_mut() {
+        for (orb_id, orb, mut used_by) in orb_query.iter_mut() {
+            if used_by.0.contains(&player_id) || rapier_context.intersection_pair(player_id, orb_id) != Some(true) {
+                continue;
+            }
+            if !*pressed.get_or_insert_with(|| input_buffer.consume(InputAction::Jump, &time)) {
+                return;
+            }
+
+            bounce(orb.color, orb.color.orb_strength(), &mut jump, &mut velocity, &mut gravity);
+            used_by.0.insert(player_id);
+        }
+    }
+
+    if pressed == Some(true) {
+        sound_ev.send(SoundEvent::Orb);
+    }
+}
+
+pub struct PadPlugin;
+impl Plugin for PadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(jump_pad_collision)
+            .add_system(jump_orb_collision.before(player_movement_jump));
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::audio::AudioSink;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::input::{ActionState, InputAction};
+use crate::music::MusicPlayer;
+use crate::player::{LevelState, RespawnPlayerEvent};
+use crate::practice::{Checkpoints, TogglePracticeEvent};
+use crate::settings::OpenSettingsEvent;
+use crate::{GameAssets, GameState, GameStateVariant};
+
+#[derive(Component)]
+struct PauseMenuMarker;
+
+#[derive(Component)]
+enum PauseButton {
+    Resume,
+    Restart,
+    TogglePractice,
+    Settings,
+    EditLevel,
+    ExitToMenu,
+}
+
+fn pause_game(
+    commands: &mut Commands,
+    time: &mut Time,
+    rapier_config: &mut RapierConfiguration,
+    music_player: &MusicPlayer,
+    sinks: &Assets<AudioSink>,
+    level_state: &mut LevelState,
+    game_assets: &GameAssets,
+) {
+    level_state.paused = true;
+    time.pause();
+    rapier_config.physics_pipeline_active = false;
+    music_player.set_paused(true, sinks);
+
+    let text_style = TextStyle {
+        font: game_assets.font_roboto_black.clone(),
+        font_size: 33.5,
+        color: Color::WHITE,
+    };
+    let practice_label = if level_state.practice { "Normal mode" } else { "Practice mode" };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..default()
+        })
+        .insert(PauseMenuMarker)
+        .insert(Name::new("PauseMenu"))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section("Paused", text_style.clone()));
+
+            for (button, label) in [
+                (PauseButton::Resume, "Resume"),
+                (PauseButton::Restart, "Restart"),
+                (PauseButton::TogglePractice, practice_label),
+                (PauseButton::Settings, "Settings"),
+                (PauseButton::EditLevel, "Editor"),
+                (PauseButton::ExitToMenu, "Menu"),
+            ] {
+                commands
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(240.0), Val::Px(60.0)),
+                            margin: UiRect::all(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::DARK_GRAY.into(),
+                        ..default()
+                    })
+                    .insert(button)
+                    .insert(Name::new(label))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(label, text_style.clone()));
+                    });
+            }
+        });
+}
+
+fn resume_game(
+    commands: &mut Commands,
+    menu_query: &Query<Entity, With<PauseMenuMarker>>,
+    time: &mut Time,
+    rapier_config: &mut RapierConfiguration,
+    music_player: &MusicPlayer,
+    sinks: &Assets<AudioSink>,
+    level_state: &mut LevelState,
+) {
+    level_state.paused = false;
+    time.unpause();
+    rapier_config.physics_pipeline_active = !level_state.completed;
+    music_player.set_paused(false, sinks);
+
+    for menu_id in menu_query.iter() {
+        commands.entity(menu_id).despawn_recursive();
+    }
+}
+
+fn toggle_pause(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    menu_query: Query<Entity, With<PauseMenuMarker>>,
+    mut time: ResMut<Time>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    music_player: Res<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut level_state: ResMut<LevelState>,
+    game_assets: Res<GameAssets>,
+) {
+    if !level_state.active || level_state.completed || !actions.just_pressed(InputAction::Pause) {
+        return;
+    }
+
+    if level_state.paused {
+        resume_game(
+            &mut commands,
+            &menu_query,
+            &mut time,
+            &mut rapier_config,
+            &music_player,
+            &sinks,
+            &mut level_state,
+        );
+    } else {
+        pause_game(
+            &mut commands,
+            &mut time,
+            &mut rapier_config,
+            &music_player,
+            &sinks,
+            &mut level_state,
+            &game_assets,
+        );
+    }
+}
+
+fn pause_button_clicked(
+    mut commands: Commands,
+    interaction: Query<(&Interaction, &PauseButton), Changed<Interaction>>,
+    menu_query: Query<Entity, With<PauseMenuMarker>>,
+    mut time: ResMut<Time>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    music_player: Res<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut level_state: ResMut<LevelState>,
+    mut game_state: ResMut<GameState>,
+    mut checkpoints: ResMut<Checkpoints>,
+    mut respawn_player_ev: EventWriter<RespawnPlayerEvent>,
+    mut toggle_practice_ev: EventWriter<TogglePracticeEvent>,
+    mut open_settings_ev: EventWriter<OpenSettingsEvent>,
+) {
+    for (interaction, button) in &interaction {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+
+        //settings keep the game paused
+        if let PauseButton::Settings = button {
+            open_settings_ev.send_default();
+            continue;
+        }
+
+        resume_game(
+            &mut commands,
+            &menu_query,
+            &mut time,
+            &mut rapier_config,
+            &music_player,
+            &sinks,
+            &mut level_state,
+        );
+
+        match button {
+            PauseButton::Resume | PauseButton::Settings => {}
+            PauseButton::Restart => {
+                checkpoints.clear(&mut commands);
+                respawn_player_ev.send_default();
+            }
+            PauseButton::TogglePractice => toggle_practice_ev.send_default(),
+            PauseButton::EditLevel => game_state.variant = GameStateVariant::Editor,
+            PauseButton::ExitToMenu => game_state.variant = GameStateVariant::Menu,
+        }
+    }
+}
+
+//a fresh attempt from the start, practice checkpoints are dropped too
+fn restart_level(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    level_state: Res<LevelState>,
+    mut checkpoints: ResMut<Checkpoints>,
+    mut respawn_player_ev: EventWriter<RespawnPlayerEvent>,
+) {
+    if level_state.active
+        && !level_state.completed
+        && !level_state.paused
+        && actions.just_pressed(InputAction::Restart)
+    {
+        checkpoints.clear(&mut commands);
+        respawn_player_ev.send_default();
+    }
+}
+
+//leaving the level some other way mustn't leave the clock stopped
+fn close_pause_menu(
+    mut commands: Commands,
+    menu_query: Query<Entity, With<PauseMenuMarker>>,
+    mut time: ResMut<Time>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    music_player: Res<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut level_state: ResMut<LevelState>,
+    game_state: Res<GameState>,
+) {
+    if level_state.paused && (!level_state.active || game_state.variant != GameStateVariant::Level) {
+        resume_game(
+            &mut commands,
+            &menu_query,
+            &mut time,
+            &mut rapier_config,
+            &music_player,
+            &sinks,
+            &mut level_state,
+        );
+    }
+}
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(toggle_pause)
+            .add_system(pause_button_clicked)
+            .add_system(restart_level)
+            .add_system(close_pause_menu);
+    }
+}
@@ -1,132 +1,374 @@
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::BufReader;
 
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::{GameAssets, GameState, GameStateVariant, GroundMarker};
+use crate::color::ColorChannels;
+use crate::console::{ConsoleAppExt, ConsoleCommandEvent, ConsoleReply};
+use crate::input::{ActionState, InputAction, InputBuffer};
+use crate::level::{level_bounds_y, level_end, level_hash, load_level, spawn_block, Activated, LevelObject, SpikeMarker};
+use crate::music::LevelMusic;
+use crate::pad::UsedBy;
+use crate::practice::Checkpoints;
+use crate::results::ResultsScreenMarker;
+use crate::sound::SoundEvent;
+use crate::{game_camera, GameAssets, GameState, GameStateVariant, GroundMarker, VIEW_HEIGHT};
 
 #[derive(Component)]
-struct PlayerMarker;
+pub struct PlayerMarker;
 #[derive(Component)]
-struct LevelCameraMarker;
+pub struct DualPlayerMarker; //the second player spawned by a dual portal
 #[derive(Component)]
-struct AttemptsTextMarker;
+pub struct LevelCameraMarker;
 #[derive(Component)]
-struct SpikeMarker;
+struct AttemptsTextMarker;
 #[derive(Component)]
-struct ClippedBlockMarker;
+struct DeathEffectMarker;
+
+const PLAYER_JUMP_VALUE: f32 = 900.0;
+pub const PLAYER_GRAVITY: f32 = 31.0;
+pub const PLAYER_SPEED: f32 = 450.0;
+const PLAYER_ROTATION_SPEED: f32 = 5.0;
+const MIRROR_TRANSITION_SPEED: f32 = 4.0;
+const DEATH_DELAY: f32 = 0.5;
+
+pub const STARTING_PLAYER_POSTION: Vec3 = Vec3::new(-600.0, -220.0, 0.0);
+pub const STARTING_CAMERA_POSTION: Vec3 = Vec3::new(-300.0, 0.0, 0.0);
+const CAMERA_OFFSET_X: f32 = STARTING_CAMERA_POSTION.x - STARTING_PLAYER_POSTION.x;
+const DUAL_CAMERA_MARGIN: f32 = 300.0;
+const MAX_DUAL_ZOOM: f32 = 2.0;
+const CAMERA_DEAD_ZONE: (f32, f32) = (-250.0, 150.0); //how far below and above the center the player can go before the camera follows
+const CAMERA_FOLLOW_SPEED: f32 = 5.0;
+const ATTEMPTS_TEXT_OFFSET: Vec3 = Vec3::new(0.0, 65.0, 0.0);
 
 #[derive(Component)]
-struct SerializableMarker; //marks every object that can be serialized
+pub struct Jump {
+    pub value: f32,
+    pub is_jumping: bool,
+    pub rotation_value: f32,
+}
+
+//the speed tiers from geometry dash, named after the in-game portals
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SpeedTier {
+    Half,
+    #[default]
+    Normal,
+    Double,
+    Triple,
+    Quadruple,
+}
+
+impl SpeedTier {
+    pub const ALL: [SpeedTier; 5] = [
+        SpeedTier::Half,
+        SpeedTier::Normal,
+        SpeedTier::Double,
+        SpeedTier::Triple,
+        SpeedTier::Quadruple,
+    ];
+
+    //horizontal speed in pixels per second, the ratios match the original game
+    pub fn value(&self) -> f32 {
+        let ratio = match self {
+            SpeedTier::Half => 0.807,
+            SpeedTier::Normal => 1.0,
+            SpeedTier::Double => 1.243,
+            SpeedTier::Triple => 1.502,
+            SpeedTier::Quadruple => 1.849,
+        };
+        PLAYER_SPEED * ratio
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            SpeedTier::Half => Color::ORANGE,
+            SpeedTier::Normal => Color::CYAN,
+            SpeedTier::Double => Color::GREEN,
+            SpeedTier::Triple => Color::PINK,
+            SpeedTier::Quadruple => Color::RED,
+        }
+    }
+}
 
 #[derive(Component)]
-enum BlockType {
-    Spike(SpikeMarker),
-    Ground(GroundMarker),
-    Clipped(ClippedBlockMarker),
+pub struct Speed {
+    pub tier: SpeedTier,
 }
 
-const PLAYER_JUMP_VALUE: f32 = 900.0;
-const PLAYER_SPEED: f32 = 450.0;
-const PLAYER_ROTATION_SPEED: f32 = 5.0;
+#[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PlayerSize {
+    #[default]
+    Normal,
+    Mini,
+}
 
-pub const STARTING_PLAYER_POSTION: Vec3 = Vec3::new(-600.0, -220.0, 0.0);
-pub const STARTING_CAMERA_POSTION: Vec3 = Vec3::new(-300.0, 0.0, 0.0);
+impl PlayerSize {
+    //the collider scales together with the sprite
+    pub fn scale(&self) -> Vec3 {
+        match self {
+            PlayerSize::Normal => Vec3::new(2.0, 2.0, 1.0),
+            PlayerSize::Mini => Vec3::new(1.2, 1.2, 1.0),
+        }
+    }
+
+    pub fn jump_ratio(&self) -> f32 {
+        match self {
+            PlayerSize::Normal => 1.0,
+            PlayerSize::Mini => 0.8,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PlayerSize::Normal => Color::LIME_GREEN,
+            PlayerSize::Mini => Color::FUCHSIA,
+        }
+    }
+}
 
 #[derive(Component)]
-struct Jump {
-    value: f32,
-    is_jumping: bool,
-    rotation_value: f32,
+pub struct CameraFraming {
+    pub mirror: f32, //1.0 normally, -1.0 when mirrored, in between while transitioning
+    pub zoom: f32,
+    pub follow_y: f32, //height the dead zone is centered on
+    //set by camera triggers
+    pub offset: Vec2,
+    pub trigger_zoom: f32,
+    pub static_position: Option<Vec2>,
+    pub section: Option<(f32, f32)>, //floor and ceiling of a fixed framing section
+}
+
+impl Default for CameraFraming {
+    fn default() -> Self {
+        CameraFraming {
+            mirror: 1.0,
+            zoom: 1.0,
+            follow_y: STARTING_CAMERA_POSTION.y,
+            offset: Vec2::ZERO,
+            trigger_zoom: 1.0,
+            static_position: None,
+            section: None,
+        }
+    }
+}
+
+//everything needed to put a player back where it was
+#[derive(Clone)]
+pub struct PlayerSnapshot {
+    pub translation: Vec3,
+    pub velocity: Vec2,
+    pub gravity: f32,
+    pub speed: SpeedTier,
+    pub size: PlayerSize,
+    pub is_jumping: bool,
+    pub rotation_value: f32,
+}
+
+impl PlayerSnapshot {
+    pub fn capture(
+        transform: &Transform,
+        velocity: &Velocity,
+        gravity: &GravityScale,
+        speed: &Speed,
+        size: &PlayerSize,
+        jump: &Jump,
+    ) -> Self {
+        PlayerSnapshot {
+            translation: transform.translation,
+            velocity: velocity.linvel,
+            gravity: gravity.0,
+            speed: speed.tier,
+            size: *size,
+            is_jumping: jump.is_jumping,
+            rotation_value: jump.rotation_value,
+        }
+    }
+
+    fn apply(
+        &self,
+        transform: &mut Transform,
+        velocity: &mut Velocity,
+        gravity: &mut GravityScale,
+        speed: &mut Speed,
+        size: &mut PlayerSize,
+        jump: &mut Jump,
+    ) {
+        transform.translation = self.translation;
+        transform.scale = self.size.scale();
+        transform.rotation = Quat::from_rotation_z(self.rotation_value * 3.1415 / 180.0 as f32);
+        velocity.linvel = self.velocity;
+        velocity.angvel = 0.0;
+        gravity.0 = self.gravity;
+        speed.tier = self.speed;
+        *size = self.size;
+        jump.is_jumping = self.is_jumping;
+        jump.rotation_value = self.rotation_value;
+    }
+}
+
+//the first player is the main one, a second one means dual mode was on
+#[derive(Clone)]
+pub struct LevelSnapshot {
+    pub players: Vec<PlayerSnapshot>,
+    pub mirrored: bool,
+    pub camera_translation: Vec3,
+    pub time: f32, //seconds since the level started, used to seek the music
+}
+
+impl LevelSnapshot {
+    fn start(starting_speed: SpeedTier) -> Self {
+        LevelSnapshot {
+            players: vec![PlayerSnapshot {
+                translation: STARTING_PLAYER_POSTION,
+                velocity: Vec2::ZERO,
+                gravity: PLAYER_GRAVITY,
+                speed: starting_speed,
+                size: PlayerSize::Normal,
+                is_jumping: true,
+                rotation_value: 0.0,
+            }],
+            mirrored: false,
+            camera_translation: STARTING_CAMERA_POSTION,
+            time: 0.0,
+        }
+    }
 }
 
 #[derive(Resource)]
 pub struct LevelState {
     pub attempts: u32,
     pub active: bool,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct BlockInfo {
-    id: u8,
-    name: String,
-    marker_type: u8, //0- spike, 1- block, 2- ClippedBlockMarker
-    coords: (i32, i32),
+    pub starting_speed: SpeedTier,
+    pub mirrored: bool, //the view is flipped so the player runs right-to-left
+    pub end_x: f32,
+    pub bounds_y: (f32, f32), //the flipped player in dual mode would rise forever without them
+    pub completed: bool,
+    pub attempt_time: f32,
+    pub level_time: f32, //unlike attempt_time it continues from a checkpoint's time
+    pub jumps: u32,
+    pub practice: bool,
+    pub start_snapshot: Option<LevelSnapshot>, //taken when the level opens, every attempt restarts from it
+    pub death_timer: Option<f32>,              //counts down between dying and respawning
+    pub death_position: Option<Vec3>,
+    pub music: Option<LevelMusic>,
+    pub level_key: String, //hash of the level file, see level_hash
+    pub paused: bool,
+    pub noclip: bool,  //set from the console, spikes don't kill
+    pub cheated: bool, //a console cheat was used this attempt, so nothing of it is recorded
+    pub spawned: bool, //objects were added from the console, they stay until the level closes
+    pub reopen: bool,  //the level was closed to be reloaded, it opens again after one closed frame
 }
 
 #[derive(Default)]
 pub struct RespawnPlayerEvent;
 
+//closes the open level or editor and opens game_state.level_path again in the same mode
+#[derive(Default)]
+pub struct ReloadLevelEvent;
+
+pub fn spawn_player(
+    commands: &mut Commands,
+    texture: Handle<Image>,
+    translation: Vec3,
+    speed: SpeedTier,
+    size: PlayerSize,
+    gravity: f32,
+) -> Entity {
+    commands
+        .spawn(SpriteBundle {
+            texture,
+            ..default()
+        })
+        .insert(Transform {
+            translation,
+            scale: size.scale(),
+            ..default()
+        })
+        .insert(RigidBody::Dynamic)
+        .insert(LockedAxes::ROTATION_LOCKED)
+        .insert(GravityScale(gravity))
+        .insert(Velocity {
+            linvel: Vec2::new(10.0, 0.0),
+            angvel: 0.0,
+        })
+        .insert(Collider::cuboid(15.0, 15.0))
+        .insert(Jump {
+            value: PLAYER_JUMP_VALUE,
+            is_jumping: true,
+            rotation_value: 0.0,
+        })
+        .insert(Speed { tier: speed })
+        .insert(size)
+        .insert(PlayerMarker)
+        .insert(Name::new("Player"))
+        .id()
+}
+
+pub fn spawn_player_from_snapshot(
+    commands: &mut Commands,
+    texture: Handle<Image>,
+    snapshot: &PlayerSnapshot,
+) -> Entity {
+    let player_id = spawn_player(
+        commands,
+        texture,
+        snapshot.translation,
+        snapshot.speed,
+        snapshot.size,
+        snapshot.gravity,
+    );
+    commands
+        .entity(player_id)
+        .insert(Transform {
+            translation: snapshot.translation,
+            rotation: Quat::from_rotation_z(snapshot.rotation_value * 3.1415 / 180.0 as f32),
+            scale: snapshot.size.scale(),
+        })
+        .insert(Velocity {
+            linvel: snapshot.velocity,
+            angvel: 0.0,
+        })
+        .insert(Jump {
+            value: PLAYER_JUMP_VALUE,
+            is_jumping: snapshot.is_jumping,
+            rotation_value: snapshot.rotation_value,
+        });
+    player_id
+}
+
 fn level_open(
     mut commands: Commands,
     game_assets: Res<GameAssets>,
     game_state: Res<GameState>,
     mut level_state: ResMut<LevelState>,
+    mut color_channels: ResMut<ColorChannels>,
 ) {
     if game_state.variant == GameStateVariant::Level && level_state.active == false {
-        let file = File::open("test_map.json").unwrap();
-        let reader = BufReader::new(file);
-        let block_info_vec: Vec<BlockInfo> = serde_json::from_reader(reader).unwrap();
-
-        for block_info in block_info_vec {
-            let block_type = match block_info.marker_type {
-                0 => BlockType::Spike(SpikeMarker),
-                1 => BlockType::Ground(GroundMarker),
-                _ => BlockType::Clipped(ClippedBlockMarker),
-            };
+        //stay closed for a frame, so systems watching level_state.active see the level end
+        if level_state.reopen {
+            level_state.reopen = false;
+            return;
+        }
 
-            commands
-                .spawn(SpriteBundle {
-                    texture: game_assets.blocks[block_info.id as usize].clone(),
-                    ..default()
-                })
-                .insert(Transform {
-                    translation: Vec3::new(block_info.coords.0 as f32, block_info.coords.1 as f32, 0.0),
-                    scale: Vec3::new(2.0, 2.0, 1.0),
-                    ..default()
-                })
-                .insert(block_type)
-                .insert(SerializableMarker)
-                .insert(Collider::cuboid(4.0, 10.0))
-                .insert(Name::new(block_info.name));
+        let level = load_level(&game_state.level_path);
+        level_state.starting_speed = level.header.starting_speed;
+        level_state.music = level.header.music.clone();
+        level_state.level_key = level_hash(&game_state.level_path);
+        color_channels.load(&level.colors);
+        level_state.end_x = level_end(&level);
+        level_state.bounds_y = level_bounds_y(&level);
+
+        for block_info in level.blocks.iter() {
+            spawn_block(&mut commands, &game_assets, block_info);
         }
 
-        commands
-            .spawn(SpriteBundle {
-                texture: game_assets.cube0.clone(),
-                ..default()
-            })
-            .insert(Transform {
-                translation: STARTING_PLAYER_POSTION,
-                scale: Vec3::new(2.0, 2.0, 1.0),
-                ..default()
-            })
-            .insert(RigidBody::Dynamic)
-            .insert(LockedAxes::ROTATION_LOCKED)
-            .insert(GravityScale(31.0))
-            .insert(Velocity {
-                linvel: Vec2::new(10.0, 0.0),
-                angvel: 0.0,
-            })
-            .insert(Collider::cuboid(15.0, 15.0))
-            .insert(Jump {
-                value: PLAYER_JUMP_VALUE,
-                is_jumping: true,
-                rotation_value: 0.0,
-            })
-            .insert(PlayerMarker)
-            .insert(Name::new("Player"));
+        let start_snapshot = LevelSnapshot::start(level_state.starting_speed);
+        spawn_player_from_snapshot(&mut commands, game_assets.cube0.clone(), &start_snapshot.players[0]);
 
         commands.spawn((
-            Camera2dBundle {
-                transform: Transform {
-                    translation: STARTING_CAMERA_POSTION,
-                    ..default()
-                },
-                ..default()
-            },
+            game_camera(start_snapshot.camera_translation),
+            CameraFraming::default(),
             LevelCameraMarker,
             Name::new("LevelCamera"),
         ));
@@ -143,30 +385,57 @@ fn level_open(
                 ..default()
             })
             .insert(Transform {
-                translation: Vec3::new(-660.0, -155.0, 0.0),
+                //above the player's starting point, so it's on screen at any window width
+                translation: STARTING_PLAYER_POSTION + ATTEMPTS_TEXT_OFFSET,
                 ..default()
             })
             .insert(AttemptsTextMarker);
 
+        level_state.attempts = 0;
+        level_state.practice = false;
+        level_state.paused = false;
+        level_state.mirrored = false;
+        level_state.completed = false;
+        level_state.attempt_time = 0.0;
+        level_state.level_time = 0.0;
+        level_state.jumps = 0;
+        level_state.death_timer = None;
+        level_state.death_position = None;
+        level_state.cheated = false;
+        level_state.spawned = false;
+        level_state.start_snapshot = Some(start_snapshot);
         level_state.active = true;
     }
 }
 
 fn level_close(
     mut commands: Commands,
-    player_query: Query<Entity, (With<PlayerMarker>, Without<LevelCameraMarker>)>,
-    camera_query: Query<Entity, With<LevelCameraMarker>>,
+    level_entities_query: Query<
+        Entity,
+        Or<(
+            With<PlayerMarker>,
+            With<LevelCameraMarker>,
+            With<AttemptsTextMarker>,
+            With<DeathEffectMarker>,
+            With<LevelObject>,
+        )>,
+    >,
+    results_query: Query<Entity, With<ResultsScreenMarker>>,
     game_state: Res<GameState>,
     mut level_state: ResMut<LevelState>,
+    mut reload_level_ev: EventReader<ReloadLevelEvent>,
 ) {
-    if game_state.variant == GameStateVariant::Editor && level_state.active == true {
-        for player_id in player_query.iter() {
-            for camera_id in camera_query.iter() {
-                commands.entity(camera_id).despawn();
-                commands.entity(player_id).despawn();
-                level_state.active = false;
-            }
+    let reload = reload_level_ev.iter().last().is_some();
+    if (game_state.variant != GameStateVariant::Level || reload) && level_state.active == true {
+        for entity in level_entities_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        for entity in results_query.iter() {
+            commands.entity(entity).despawn_recursive();
         }
+        level_state.active = false;
+        level_state.noclip = false;
+        level_state.reopen = reload && game_state.variant == GameStateVariant::Level;
     }
 }
 
@@ -176,7 +445,7 @@ fn update_attemps_text(
     mut attempts_text: Query<&mut Text, With<AttemptsTextMarker>>,
     game_assets: Res<GameAssets>,
 ) {
-    for _ in respawn_player_ev.iter() {
+    if respawn_player_ev.iter().last().is_some() {
         for mut text in attempts_text.iter_mut() {
             level_state.attempts += 1;
 
@@ -195,31 +464,117 @@ fn update_attemps_text(
 }
 
 fn player_movement_linear(
-    mut player_query: Query<&mut Transform, (With<PlayerMarker>, Without<LevelCameraMarker>)>,
-    mut camera_query: Query<&mut Transform, With<LevelCameraMarker>>,
+    mut player_query: Query<(&mut Transform, &Speed), With<PlayerMarker>>,
     time: Res<Time>,
     level_state: Res<LevelState>,
 ) {
-    if level_state.active {
-        for mut transform in player_query.iter_mut() {
-            for mut camera_transform in camera_query.iter_mut() {
-                transform.translation.x += PLAYER_SPEED * time.delta_seconds();
-                camera_transform.translation.x += PLAYER_SPEED * time.delta_seconds();
+    if level_state.active && !level_state.completed && !level_state.paused && level_state.death_timer.is_none() {
+        for (mut transform, speed) in player_query.iter_mut() {
+            transform.translation.x += speed.tier.value() * time.delta_seconds();
+        }
+    }
+}
+
+//follows the player horizontally, in dual mode it also keeps both players in the frame
+fn level_camera_follow(
+    player_query: Query<&Transform, (With<PlayerMarker>, Without<LevelCameraMarker>)>,
+    mut camera_query: Query<(&mut Transform, &mut CameraFraming), With<LevelCameraMarker>>,
+    time: Res<Time>,
+    level_state: Res<LevelState>,
+) {
+    if !level_state.active {
+        return;
+    }
+
+    let mut min = Vec2::splat(f32::MAX);
+    let mut max = Vec2::splat(f32::MIN);
+    let mut player_count = 0;
+    for transform in player_query.iter() {
+        min = min.min(transform.translation.truncate());
+        max = max.max(transform.translation.truncate());
+        player_count += 1;
+    }
+    if player_count == 0 {
+        return;
+    }
+
+    for (mut camera_transform, mut framing) in camera_query.iter_mut() {
+        let mut target = Vec2::new(max.x + CAMERA_OFFSET_X, camera_transform.translation.y);
+        framing.zoom = 1.0;
+
+        if let Some(position) = framing.static_position {
+            target = position;
+        } else if let Some((floor, ceiling)) = framing.section {
+            target.y = (floor + ceiling) / 2.0;
+            framing.zoom = ((ceiling - floor) / VIEW_HEIGHT).max(1.0);
+        } else if player_count > 1 {
+            target.y = (min.y + max.y) / 2.0;
+            framing.zoom = ((max.y - min.y + DUAL_CAMERA_MARGIN) / VIEW_HEIGHT).clamp(1.0, MAX_DUAL_ZOOM);
+        } else {
+            //the camera only moves up or down once the player leaves the dead zone
+            if max.y > framing.follow_y + CAMERA_DEAD_ZONE.1 {
+                framing.follow_y = max.y - CAMERA_DEAD_ZONE.1;
+            } else if max.y < framing.follow_y + CAMERA_DEAD_ZONE.0 {
+                framing.follow_y = max.y - CAMERA_DEAD_ZONE.0;
             }
+            framing.follow_y = framing.follow_y.max(STARTING_CAMERA_POSTION.y);
+            target.y = framing.follow_y;
+        }
+        target += framing.offset;
+
+        let smoothing = (CAMERA_FOLLOW_SPEED * time.delta_seconds()).min(1.0);
+        if framing.static_position.is_some() {
+            camera_transform.translation.x += (target.x - camera_transform.translation.x) * smoothing;
+        } else {
+            camera_transform.translation.x = target.x;
         }
+        camera_transform.translation.y += (target.y - camera_transform.translation.y) * smoothing;
+
+        let zoom = framing.zoom * framing.trigger_zoom;
+        camera_transform.scale = Vec3::new(framing.mirror * zoom, zoom, 1.0);
     }
 }
 
-fn player_movement_jump(
-    mut player_query: Query<(&mut Jump, &mut Velocity), With<PlayerMarker>>,
-    keys: Res<Input<KeyCode>>,
+//the camera scale is flipped instead of the level, so mirrored levels play the same
+fn update_camera_mirror(
+    mut camera_query: Query<&mut CameraFraming, With<LevelCameraMarker>>,
+    time: Res<Time>,
     level_state: Res<LevelState>,
 ) {
-    if level_state.active {
-        for (mut jump, mut velocity) in player_query.iter_mut() {
-            if keys.pressed(KeyCode::Up) && !jump.is_jumping {
-                velocity.linvel = Vec2::new(0.0, jump.value).into();
+    let target = if level_state.mirrored { -1.0 } else { 1.0 };
+    for mut framing in camera_query.iter_mut() {
+        let step = MIRROR_TRANSITION_SPEED * time.delta_seconds();
+        let difference = target - framing.mirror;
+        if difference.abs() <= step {
+            framing.mirror = target;
+        } else {
+            framing.mirror += step * difference.signum();
+        }
+    }
+}
+
+//the cube jumps while the button is held, a press made just before landing is kept for the landing
+pub fn player_movement_jump(
+    mut player_query: Query<(&mut Jump, &mut Velocity, &PlayerSize, &GravityScale), With<PlayerMarker>>,
+    actions: Res<ActionState>,
+    time: Res<Time>,
+    mut input_buffer: ResMut<InputBuffer>,
+    mut level_state: ResMut<LevelState>,
+    mut sound_ev: EventWriter<SoundEvent>,
+) {
+    if level_state.active && !level_state.completed && !level_state.paused && level_state.death_timer.is_none() {
+        for (mut jump, mut velocity, size, gravity) in player_query.iter_mut() {
+            if jump.is_jumping {
+                continue;
+            }
+            //the press is used up either way, so it can't also hit an orb right after
+            let buffered = input_buffer.consume(InputAction::Jump, &time);
+            if actions.pressed(InputAction::Jump) || buffered {
+                let jump_value = jump.value * size.jump_ratio() * gravity.0.signum();
+                velocity.linvel = Vec2::new(0.0, jump_value).into();
                 jump.is_jumping = true;
+                level_state.jumps += 1;
+                sound_ev.send(SoundEvent::Jump);
             }
         }
     }
@@ -266,31 +621,234 @@ fn reset_player_jump(
 }
 
 fn player_death(
-    mut player_query: Query<Entity, (With<PlayerMarker>, Without<SpikeMarker>)>,
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &Transform, &mut Visibility), (With<PlayerMarker>, Without<SpikeMarker>)>,
     mut spike_queries: Query<Entity, With<SpikeMarker>>,
     rapier_context: Res<RapierContext>,
-    mut respawn_player_ev: EventWriter<RespawnPlayerEvent>,
+    mut level_state: ResMut<LevelState>,
+    mut sound_ev: EventWriter<SoundEvent>,
 ) {
-    for player_id in player_query.iter_mut() {
+    if level_state.completed || level_state.death_timer.is_some() {
+        return;
+    }
+
+    let mut death_position = None;
+    let (bottom, top) = level_state.bounds_y;
+    for (player_id, transform, _) in player_query.iter() {
+        //noclip doesn't save a player that left the level
+        if transform.translation.y < bottom || transform.translation.y > top {
+            death_position = Some(transform.translation);
+        }
+        if level_state.noclip {
+            continue;
+        }
         for spike_id in spike_queries.iter_mut() {
             if let Some(_contact_pair) = rapier_context.contact_pair(player_id, spike_id) {
-                respawn_player_ev.send_default();
+                death_position = Some(transform.translation);
             }
         }
     }
+
+    //the respawn waits for the death animation, see tick_death_timer
+    if let Some(position) = death_position {
+        level_state.death_timer = Some(DEATH_DELAY);
+        level_state.death_position = Some(position);
+        sound_ev.send(SoundEvent::Death);
+
+        for (_, _, mut visibility) in player_query.iter_mut() {
+            visibility.is_visible = false;
+        }
+
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::ORANGE,
+                    custom_size: Some(Vec2::new(32.0, 32.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position + Vec3::Z),
+                ..default()
+            })
+            .insert(DeathEffectMarker)
+            .insert(Name::new("DeathEffect"));
+    }
 }
 
-fn reset_player_state(
+fn tick_death_timer(
+    mut effect_query: Query<(&mut Transform, &mut Sprite), With<DeathEffectMarker>>,
+    time: Res<Time>,
+    mut level_state: ResMut<LevelState>,
+    mut respawn_player_ev: EventWriter<RespawnPlayerEvent>,
+) {
+    if let Some(timer) = level_state.death_timer {
+        let timer = timer - time.delta_seconds();
+        level_state.death_timer = Some(timer);
+
+        //the effect grows and fades out over the delay
+        let progress = 1.0 - (timer / DEATH_DELAY).max(0.0);
+        for (mut transform, mut sprite) in effect_query.iter_mut() {
+            transform.scale = Vec3::splat(1.0 + progress * 3.0);
+            sprite.color.set_a(1.0 - progress);
+        }
+
+        if timer <= 0.0 {
+            respawn_player_ev.send_default();
+        }
+    }
+}
+
+pub fn reset_player_state(
+    mut commands: Commands,
     mut respawn_player_ev: EventReader<RespawnPlayerEvent>,
-    mut player_query: Query<&mut Transform, (With<PlayerMarker>, Without<LevelCameraMarker>)>,
-    mut camera_query: Query<&mut Transform, With<LevelCameraMarker>>,
+    mut player_query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut GravityScale,
+            &mut Speed,
+            &mut PlayerSize,
+            &mut Jump,
+            &mut Visibility,
+        ),
+        (With<PlayerMarker>, Without<DualPlayerMarker>, Without<LevelCameraMarker>),
+    >,
+    dual_query: Query<Entity, With<DualPlayerMarker>>,
+    effect_query: Query<Entity, With<DeathEffectMarker>>,
+    mut activated_query: Query<&mut Activated>,
+    mut used_query: Query<&mut UsedBy>,
+    mut camera_query: Query<(&mut Transform, &mut CameraFraming), With<LevelCameraMarker>>,
+    mut level_state: ResMut<LevelState>,
+    checkpoints: Res<Checkpoints>,
+    game_assets: Res<GameAssets>,
+) {
+    //either player dying restarts the attempt, so several events in one frame count as one
+    if respawn_player_ev.iter().last().is_none() {
+        return;
+    }
+
+    let snapshot = match (checkpoints.last(), &level_state.start_snapshot) {
+        (Some(checkpoint), _) if level_state.practice => checkpoint.clone(),
+        (_, Some(start_snapshot)) => start_snapshot.clone(),
+        _ => return,
+    };
+
+    for dual_id in dual_query.iter() {
+        commands.entity(dual_id).despawn();
+    }
+    for effect_id in effect_query.iter() {
+        commands.entity(effect_id).despawn();
+    }
+    for mut activated in activated_query.iter_mut() {
+        activated.0 = false;
+    }
+    for mut used_by in used_query.iter_mut() {
+        used_by.0.clear();
+    }
+
+    level_state.mirrored = snapshot.mirrored;
+    level_state.attempt_time = 0.0;
+    level_state.level_time = snapshot.time;
+    level_state.death_timer = None;
+    level_state.cheated = level_state.noclip || level_state.spawned;
+    for (mut transform, mut velocity, mut gravity, mut speed, mut size, mut jump, mut visibility) in
+        player_query.iter_mut()
+    {
+        snapshot.players[0].apply(
+            &mut transform,
+            &mut velocity,
+            &mut gravity,
+            &mut speed,
+            &mut size,
+            &mut jump,
+        );
+        visibility.is_visible = true;
+    }
+
+    for dual in snapshot.players.iter().skip(1) {
+        let dual_id = spawn_player_from_snapshot(&mut commands, game_assets.cube1.clone(), dual);
+        commands
+            .entity(dual_id)
+            .insert(DualPlayerMarker)
+            .insert(Name::new("DualPlayer"));
+    }
+
+    for (mut camera_transform, mut framing) in camera_query.iter_mut() {
+        //camera triggers passed before a checkpoint apply again once the player moves
+        *framing = CameraFraming {
+            mirror: if snapshot.mirrored { -1.0 } else { 1.0 },
+            follow_y: snapshot.camera_translation.y,
+            ..default()
+        };
+        camera_transform.translation = snapshot.camera_translation;
+        camera_transform.scale = Vec3::new(framing.mirror, 1.0, 1.0);
+    }
+}
+
+//the cheats from the console, they only work while a level is open
+fn player_console_commands(
+    mut command_ev: EventReader<ConsoleCommandEvent>,
+    mut reply_ev: EventWriter<ConsoleReply>,
+    mut player_query: Query<(&mut Transform, &mut Speed, &mut GravityScale), With<PlayerMarker>>,
+    mut level_state: ResMut<LevelState>,
 ) {
-    for _ in respawn_player_ev.iter() {
-        for mut player_transform in player_query.iter_mut() {
-            for mut camera_transform in camera_query.iter_mut() {
-                player_transform.translation = STARTING_PLAYER_POSTION;
-                camera_transform.translation = STARTING_CAMERA_POSTION;
+    for command in command_ev.iter() {
+        if !matches!(command.name.as_str(), "tp" | "speed" | "gravity" | "noclip") {
+            continue;
+        }
+        if !level_state.active {
+            reply_ev.send(ConsoleReply::new("Only works while playing a level"));
+            continue;
+        }
+        let arg = command.args.first().map(|arg| arg.as_str());
+
+        match (command.name.as_str(), arg) {
+            ("tp", Some(arg)) => match arg.parse::<f32>() {
+                Ok(x) => {
+                    for (mut transform, _, _) in player_query.iter_mut() {
+                        transform.translation.x = x;
+                    }
+                    level_state.cheated = true;
+                    reply_ev.send(ConsoleReply(format!("Moved to x {}", x)));
+                }
+                Err(_) => reply_ev.send(ConsoleReply(format!("'{}' isn't a number", arg))),
+            },
+            ("speed", Some(arg)) => {
+                match SpeedTier::ALL.iter().find(|tier| format!("{:?}", tier).eq_ignore_ascii_case(arg)) {
+                    Some(tier) => {
+                        for (_, mut speed, _) in player_query.iter_mut() {
+                            speed.tier = *tier;
+                        }
+                        level_state.cheated = true;
+                        reply_ev.send(ConsoleReply(format!("Speed set to {:?}", tier)));
+                    }
+                    None => reply_ev.send(ConsoleReply(format!("Unknown speed, one of {:?}", SpeedTier::ALL))),
+                }
+            }
+            ("gravity", Some("flip")) => {
+                for (_, _, mut gravity) in player_query.iter_mut() {
+                    gravity.0 = -gravity.0;
+                }
+                level_state.cheated = true;
+                reply_ev.send(ConsoleReply::new("Gravity flipped"));
+            }
+            ("gravity", Some(arg)) => match arg.parse::<f32>() {
+                Ok(value) => {
+                    for (_, _, mut gravity) in player_query.iter_mut() {
+                        gravity.0 = value;
+                    }
+                    level_state.cheated = true;
+                    reply_ev.send(ConsoleReply(format!("Gravity set to {}", value)));
+                }
+                Err(_) => reply_ev.send(ConsoleReply(format!("'{}' isn't a number", arg))),
+            },
+            ("noclip", _) => {
+                level_state.noclip = !level_state.noclip;
+                level_state.cheated |= level_state.noclip;
+                let state = if level_state.noclip { "on" } else { "off" };
+                reply_ev.send(ConsoleReply(format!("Noclip {}", state)));
             }
+            (name, None) => reply_ev.send(ConsoleReply(format!("'{}' needs an argument, see 'help'", name))),
+            _ => {}
         }
     }
 }
@@ -299,14 +857,23 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<RespawnPlayerEvent>()
-            .add_system(level_open)
+            .add_event::<ReloadLevelEvent>()
+            .add_console_command("tp", "tp <x> - moves the player to the x position")
+            .add_console_command("speed", "speed <tier> - sets the player's speed, e.g. speed double")
+            .add_console_command("gravity", "gravity <scale|flip> - sets or flips the player's gravity")
+            .add_console_command("noclip", "toggles dying on spikes")
+            .add_system(level_open.before(level_close))
             .add_system(level_close)
             .add_system(player_movement_linear)
+            .add_system(level_camera_follow.after(player_movement_linear))
+            .add_system(update_camera_mirror.before(level_camera_follow))
             .add_system(player_movement_jump)
             .add_system(player_jump_animation.before(reset_player_jump))
             .add_system(reset_player_jump)
             .add_system(player_death)
+            .add_system(tick_death_timer.after(player_death))
             .add_system(update_attemps_text)
-            .add_system(reset_player_state);
+            .add_system(reset_player_state)
+            .add_system(player_console_commands);
     }
 }
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::console::{ConsoleAppExt, ConsoleCommandEvent, ConsoleReply};
+use crate::player::{
+    spawn_player, DualPlayerMarker, LevelState, PlayerMarker, PlayerSize, Speed, SpeedTier,
+};
+use crate::GameAssets;
+
+#[derive(Component)]
+pub struct SpeedPortal {
+    pub tier: SpeedTier,
+}
+#[derive(Component)]
+pub struct SizePortal {
+    pub size: PlayerSize,
+}
+#[derive(Component)]
+pub struct MirrorPortal {
+    pub mirrored: bool,
+}
+#[derive(Component)]
+pub struct DualPortal {
+    pub dual: bool, //false merges the two players back into one
+}
+
+const DUAL_SPAWN_OFFSET: f32 = 100.0;
+
+fn speed_portal_collision(
+    mut player_query: Query<(Entity, &mut Speed), With<PlayerMarker>>,
+    portal_query: Query<(Entity, &SpeedPortal)>,
+    rapier_context: Res<RapierContext>,
+    level_state: Res<LevelState>,
+) {
+    if level_state.active {
+        for (player_id, mut speed) in player_query.iter_mut() {
+            for (portal_id, portal) in portal_query.iter() {
+                if rapier_context.intersection_pair(player_id, portal_id) == Some(true) {
+                    speed.tier = portal.tier;
+                }
+            }
+        }
+    }
+}
+
+fn size_portal_collision(
+    mut player_query: Query<(Entity, &mut PlayerSize, &mut Transform), With<PlayerMarker>>,
+    portal_query: Query<(Entity, &SizePortal), Without<PlayerMarker>>,
+    rapier_context: Res<RapierContext>,
+    level_state: Res<LevelState>,
+) {
+    if level_state.active {
+        for (player_id, mut size, mut transform) in player_query.iter_mut() {
+            for (portal_id, portal) in portal_query.iter() {
+                if rapier_context.intersection_pair(player_id, portal_id) == Some(true)
+                    && *size != portal.size
+                {
+                    *size = portal.size;
+                    transform.scale = portal.size.scale();
+                }
+            }
+        }
+    }
+}
+
+fn mirror_portal_collision(
+    player_query: Query<Entity, With<PlayerMarker>>,
+    portal_query: Query<(Entity, &MirrorPortal)>,
+    rapier_context: Res<RapierContext>,
+    mut level_state: ResMut<LevelState>,
+) {
+    if level_state.active {
+        for player_id in player_query.iter() {
+            for (portal_id, portal) in portal_query.iter() {
+                if rapier_context.intersection_pair(player_id, portal_id) == Some(true) {
+                    level_state.mirrored = portal.mirrored;
+                }
+            }
+        }
+    }
+}
+
+fn dual_portal_collision(
+    mut commands: Commands,
+    player_query: Query<
+        (&Transform, &Speed, &PlayerSize, &GravityScale),
+        (With<PlayerMarker>, Without<DualPlayerMarker>),
+    >,
+    dual_query: Query<Entity, With<DualPlayerMarker>>,
+    all_players_query: Query<Entity, With<PlayerMarker>>,
+    portal_query: Query<(Entity, &DualPortal)>,
+    rapier_context: Res<RapierContext>,
+    level_state: Res<LevelState>,
+    game_assets: Res<GameAssets>,
+) {
+    if !level_state.active {
+        return;
+    }
+
+    for (portal_id, portal) in portal_query.iter() {
+        let touched = all_players_query
+            .iter()
+            .any(|player_id| rapier_context.intersection_pair(player_id, portal_id) == Some(true));
+        if !touched {
+            continue;
+        }
+
+        if portal.dual && dual_query.is_empty() {
+            for (transform, speed, size, gravity) in player_query.iter() {
+                spawn_dual_player(&mut commands, &game_assets, transform, speed, size, gravity);
+            }
+        } else if !portal.dual {
+            for dual_id in dual_query.iter() {
+                commands.entity(dual_id).despawn();
+            }
+        }
+    }
+}
+
+//the second player starts next to the first one with its gravity flipped
+fn spawn_dual_player(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    transform: &Transform,
+    speed: &Speed,
+    size: &PlayerSize,
+    gravity: &GravityScale,
+) {
+    let dual_id = spawn_player(
+        commands,
+        game_assets.cube1.clone(),
+        transform.translation + Vec3::new(0.0, DUAL_SPAWN_OFFSET * gravity.0.signum(), 0.0),
+        speed.tier,
+        *size,
+        -gravity.0,
+    );
+    commands
+        .entity(dual_id)
+        .insert(DualPlayerMarker)
+        .insert(Name::new("DualPlayer"));
+}
+
+//mode <normal|mini|dual|single>, does what the size and dual portals do
+fn mode_command(
+    mut commands: Commands,
+    mut command_ev: EventReader<ConsoleCommandEvent>,
+    mut reply_ev: EventWriter<ConsoleReply>,
+    mut player_query: Query<
+        (&mut Transform, &Speed, &mut PlayerSize, &GravityScale),
+        (With<PlayerMarker>, Without<DualPlayerMarker>),
+    >,
+    mut dual_query: Query<(Entity, &mut Transform, &mut PlayerSize), With<DualPlayerMarker>>,
+    mut level_state: ResMut<LevelState>,
+    game_assets: Res<GameAssets>,
+) {
+    for command in command_ev.iter().filter(|command| command.name == "mode") {
+        if !level_state.active {
+            reply_ev.send(ConsoleReply::new("Only works while playing a level"));
+            continue;
+        }
+
+        match command.args.first().map(|arg| arg.as_str()) {
+            Some(arg @ ("normal" | "mini")) => {
+                let new_size = if arg == "mini" { PlayerSize::Mini } else { PlayerSize::Normal };
+                for (mut transform, _, mut size, _) in player_query.iter_mut() {
+                    *size = new_size;
+                    transform.scale = new_size.scale();
+                }
+                for (_, mut transform, mut size) in dual_query.iter_mut() {
+                    *size = new_size;
+                    transform.scale = new_size.scale();
+                }
+            }
+            Some("dual") => {
+                if dual_query.is_empty() {
+                    for (transform, speed, size, gravity) in player_query.iter() {
+                        spawn_dual_player(&mut commands, &game_assets, transform, speed, size, gravity);
+                    }
+                }
+            }
+            Some("single") => {
+                for (dual_id, _, _) in dual_query.iter() {
+                    commands.entity(dual_id).despawn();
+                }
+            }
+            _ => {
+                reply_ev.send(ConsoleReply::new("Usage: mode <normal|mini|dual|single>"));
+                continue;
+            }
+        }
+        level_state.cheated = true;
+        reply_ev.send(ConsoleReply(format!("Mode set to {}", command.args[0])));
+    }
+}
+
+pub struct PortalPlugin;
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command("mode", "mode <normal|mini|dual|single> - switches the player's size or dual mode")
+            .add_system(speed_portal_collision)
+            .add_system(size_portal_collision)
+            .add_system(mirror_portal_collision)
+            .add_system(dual_portal_collision)
+            .add_system(mode_command);
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::input::{ActionState, InputAction};
+use crate::player::{
+    DualPlayerMarker, Jump, LevelCameraMarker, LevelSnapshot, LevelState, PlayerMarker, PlayerSize,
+    PlayerSnapshot, RespawnPlayerEvent, Speed,
+};
+
+const AUTO_CHECKPOINT_SECONDS: f32 = 2.0;
+
+#[derive(Component)]
+struct CheckpointMarker;
+
+struct Checkpoint {
+    snapshot: LevelSnapshot,
+    entity: Entity, //the diamond drawn where the checkpoint was placed
+}
+
+#[derive(Resource, Default)]
+pub struct Checkpoints {
+    list: Vec<Checkpoint>,
+    auto_timer: f32,
+}
+
+impl Checkpoints {
+    pub fn last(&self) -> Option<&LevelSnapshot> {
+        self.list.last().map(|checkpoint| &checkpoint.snapshot)
+    }
+
+    pub fn clear(&mut self, commands: &mut Commands) {
+        for checkpoint in self.list.drain(..) {
+            commands.entity(checkpoint.entity).despawn();
+        }
+        self.auto_timer = 0.0;
+    }
+}
+
+//sent by the pause menu, does the same as the practice key
+#[derive(Default)]
+pub struct TogglePracticeEvent;
+
+fn toggle_practice(
+    actions: Res<ActionState>,
+    mut toggle_practice_ev: EventReader<TogglePracticeEvent>,
+    mut level_state: ResMut<LevelState>,
+    mut respawn_player_ev: EventWriter<RespawnPlayerEvent>,
+) {
+    let requested = toggle_practice_ev.iter().last().is_some();
+    let pressed = !level_state.paused && actions.just_pressed(InputAction::TogglePractice);
+    if level_state.active && !level_state.completed && (requested || pressed) {
+        level_state.practice = !level_state.practice;
+        //leaving practice mode starts a normal attempt from the beginning
+        if !level_state.practice {
+            respawn_player_ev.send_default();
+        }
+    }
+}
+
+fn place_checkpoints(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    time: Res<Time>,
+    player_query: Query<
+        (
+            &Transform,
+            &Velocity,
+            &GravityScale,
+            &Speed,
+            &PlayerSize,
+            &Jump,
+            Option<&DualPlayerMarker>,
+        ),
+        With<PlayerMarker>,
+    >,
+    camera_query: Query<&Transform, With<LevelCameraMarker>>,
+    level_state: Res<LevelState>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    if !level_state.active || !level_state.practice || level_state.completed || level_state.paused {
+        return;
+    }
+
+    let mut players = Vec::new();
+    let mut on_ground = false;
+    for (transform, velocity, gravity, speed, size, jump, dual) in player_query.iter() {
+        let snapshot = PlayerSnapshot::capture(transform, velocity, gravity, speed, size, jump);
+        //the main player always goes first
+        if dual.is_some() {
+            players.push(snapshot);
+        } else {
+            on_ground = !jump.is_jumping;
+            players.insert(0, snapshot);
+        }
+    }
+    if players.is_empty() {
+        return;
+    }
+
+    checkpoints.auto_timer += time.delta_seconds();
+    let automatic = on_ground && checkpoints.auto_timer >= AUTO_CHECKPOINT_SECONDS;
+    if !automatic && !actions.just_pressed(InputAction::PlaceCheckpoint) {
+        return;
+    }
+    checkpoints.auto_timer = 0.0;
+
+    let entity = commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::LIME_GREEN,
+                custom_size: Some(Vec2::new(16.0, 16.0)),
+                ..default()
+            },
+            transform: Transform {
+                translation: players[0].translation,
+                rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(CheckpointMarker)
+        .insert(Name::new("Checkpoint"))
+        .id();
+
+    let camera_translation = camera_query
+        .iter()
+        .next()
+        .map(|transform| transform.translation)
+        .unwrap_or_default();
+
+    checkpoints.list.push(Checkpoint {
+        snapshot: LevelSnapshot {
+            players,
+            mirrored: level_state.mirrored,
+            camera_translation,
+            time: level_state.level_time,
+        },
+        entity,
+    });
+}
+
+fn delete_checkpoint(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    level_state: Res<LevelState>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    if level_state.active && level_state.practice && !level_state.paused && actions.just_pressed(InputAction::DeleteCheckpoint) {
+        if let Some(checkpoint) = checkpoints.list.pop() {
+            commands.entity(checkpoint.entity).despawn();
+        }
+    }
+}
+
+//checkpoints only live as long as practice mode does
+fn clear_checkpoints(
+    mut commands: Commands,
+    checkpoint_query: Query<Entity, With<CheckpointMarker>>,
+    level_state: Res<LevelState>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    if (!level_state.active || !level_state.practice) && !checkpoints.list.is_empty() {
+        for checkpoint_id in checkpoint_query.iter() {
+            commands.entity(checkpoint_id).despawn();
+        }
+        checkpoints.list.clear();
+        checkpoints.auto_timer = 0.0;
+    }
+}
+
+pub struct PracticePlugin;
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Checkpoints>()
+            .add_event::<TogglePracticeEvent>()
+            .add_system(toggle_practice)
+            .add_system(place_checkpoints)
+            .add_system(delete_checkpoint)
+            .add_system(clear_checkpoints);
+    }
+}
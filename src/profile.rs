@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::coin::MAX_COINS;
+use crate::player::{LevelState, RespawnPlayerEvent};
+use crate::results::LevelCompletedEvent;
+
+const PROFILE_DIRECTORY: &str = "geometry_dash";
+const PROFILE_FILE: &str = "profile.json";
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LevelStats {
+    pub attempts: u32,
+    pub jumps: u32,
+    pub best_normal: f32,
+    pub best_practice: f32,
+    pub best_time: Option<f32>, //fastest normal completion
+    pub coins: [bool; MAX_COINS],
+}
+
+//everything the player did, per level, keyed by the hash of the level file
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct Profile {
+    #[serde(default)]
+    pub levels: HashMap<String, LevelStats>,
+    #[serde(skip)]
+    pub unsaved: bool, //written out once the level is closed or completed or the game exits, see save_changed_profile
+}
+
+impl Profile {
+    pub fn stats(&self, level_key: &str) -> LevelStats {
+        self.levels.get(level_key).cloned().unwrap_or_default()
+    }
+
+    pub fn stats_mut(&mut self, level_key: &str) -> &mut LevelStats {
+        self.levels.entry(level_key.to_string()).or_default()
+    }
+
+    //keeps the percent if it beats the record
+    pub fn record_percent(&mut self, level_key: &str, percent: f32, practice: bool) {
+        let stats = self.stats_mut(level_key);
+        let best = if practice { &mut stats.best_practice } else { &mut stats.best_normal };
+        if percent > *best {
+            *best = percent;
+            self.unsaved = true;
+        }
+    }
+
+    pub fn collect_coin(&mut self, level_key: &str, index: u8) {
+        if let Some(collected) = self.stats_mut(level_key).coins.get_mut(index as usize) {
+            if !*collected {
+                *collected = true;
+                self.unsaved = true;
+            }
+        }
+    }
+}
+
+fn profile_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(PROFILE_DIRECTORY)
+        .join(PROFILE_FILE)
+}
+
+//a profile that can't be read is moved aside instead of being overwritten
+fn load_profile() -> Profile {
+    let path = profile_path();
+    match File::open(&path) {
+        Ok(file) => match serde_json::from_reader(BufReader::new(file)) {
+            Ok(profile) => profile,
+            Err(error) => {
+                let backup = path.with_extension("corrupt.json");
+                println!("The profile is corrupt ({}), it was moved to {}", error, backup.display());
+                let _ = fs::rename(&path, &backup);
+                Profile::default()
+            }
+        },
+        Err(_) => Profile::default(),
+    }
+}
+
+//written to a temporary file first, so a crash mid-write never leaves half a file behind
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let temporary = path.with_extension("json.tmp");
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+fn save_profile(profile: &Profile) {
+    let result = serde_json::to_vec_pretty(profile)
+        .map_err(std::io::Error::from)
+        .and_then(|contents| write_atomically(&profile_path(), &contents));
+
+    if let Err(error) = result {
+        println!("Couldn't save the profile: {}", error);
+    }
+}
+
+//attempts are counted when they start, jumps are saved together with the attempt
+fn track_level_stats(
+    mut respawn_player_ev: EventReader<RespawnPlayerEvent>,
+    level_state: Res<LevelState>,
+    mut profile: ResMut<Profile>,
+    mut playing_level: Local<bool>,
+    mut counted_jumps: Local<u32>,
+) {
+    let respawned = respawn_player_ev.iter().last().is_some();
+    let opened = level_state.active && !*playing_level;
+    let closed = !level_state.active && *playing_level;
+    *playing_level = level_state.active;
+
+    if opened {
+        *counted_jumps = 0;
+    }
+    if !level_state.active && !closed {
+        return;
+    }
+
+    let new_jumps = level_state.jumps.saturating_sub(*counted_jumps);
+    *counted_jumps = level_state.jumps;
+    let stats = profile.stats_mut(&level_state.level_key);
+    stats.jumps += new_jumps;
+    if opened || respawned {
+        stats.attempts += 1;
+    }
+    if opened || respawned || closed || new_jumps > 0 {
+        profile.unsaved = true;
+    }
+}
+
+fn record_completion_time(
+    mut level_completed_ev: EventReader<LevelCompletedEvent>,
+    level_state: Res<LevelState>,
+    mut profile: ResMut<Profile>,
+) {
+    if level_completed_ev.iter().last().is_none() || level_state.practice || level_state.cheated {
+        return;
+    }
+
+    let stats = profile.stats_mut(&level_state.level_key);
+    if stats.best_time.map_or(true, |best| level_state.attempt_time < best) {
+        stats.best_time = Some(level_state.attempt_time);
+        profile.unsaved = true;
+    }
+}
+
+//every respawn changes the profile, so it's only written between plays instead of each time
+fn save_changed_profile(level_state: Res<LevelState>, mut profile: ResMut<Profile>) {
+    if profile.unsaved && (!level_state.active || level_state.completed) {
+        save_profile(&profile);
+        profile.unsaved = false;
+    }
+}
+
+//the game can be closed in the middle of a level, runs last so the window closing is seen too
+fn save_profile_on_exit(mut app_exit_ev: EventReader<AppExit>, mut profile: ResMut<Profile>) {
+    if app_exit_ev.iter().last().is_some() && profile.unsaved {
+        save_profile(&profile);
+        profile.unsaved = false;
+    }
+}
+
+pub struct ProfilePlugin;
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_profile())
+            .add_system(track_level_stats)
+            .add_system(record_completion_time)
+            .add_system(save_changed_profile.after(track_level_stats).after(record_completion_time))
+            .add_system_to_stage(CoreStage::Last, save_profile_on_exit);
+    }
+}
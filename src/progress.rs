@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+
+use crate::player::{LevelState, PlayerMarker, RespawnPlayerEvent, STARTING_PLAYER_POSTION};
+use crate::profile::Profile;
+use crate::results::LevelCompletedEvent;
+use crate::settings::Settings;
+use crate::GameAssets;
+
+#[derive(Component)]
+struct ProgressBarMarker;
+#[derive(Component)]
+struct ProgressBarFillMarker;
+#[derive(Component)]
+struct ProgressTextMarker;
+
+//how far the current attempt got, the records live in the profile
+#[derive(Resource, Default)]
+struct CurrentProgress {
+    percent: f32,
+    practice: bool, //the mode the percent was reached in, it can change right before the respawn
+    cheated: bool,  //the same for cheats, the respawn clears them
+}
+
+fn spawn_progress_bar(
+    mut commands: Commands,
+    bar_query: Query<Entity, With<ProgressBarMarker>>,
+    level_state: Res<LevelState>,
+    game_assets: Res<GameAssets>,
+) {
+    if !level_state.active || !bar_query.is_empty() {
+        return;
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Px(40.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(ProgressBarMarker)
+        .insert(Name::new("ProgressBar"))
+        .with_children(|commands| {
+            commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(40.0), Val::Px(14.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                    ..default()
+                })
+                .with_children(|commands| {
+                    commands
+                        .spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: Color::LIME_GREEN.into(),
+                            ..default()
+                        })
+                        .insert(ProgressBarFillMarker);
+                });
+
+            commands
+                .spawn(
+                    TextBundle::from_section(
+                        "0%",
+                        TextStyle {
+                            font: game_assets.font_roboto_black.clone(),
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect::left(Val::Px(10.0)),
+                        ..default()
+                    }),
+                )
+                .insert(ProgressTextMarker);
+        });
+}
+
+fn despawn_progress_bar(
+    mut commands: Commands,
+    bar_query: Query<Entity, With<ProgressBarMarker>>,
+    level_state: Res<LevelState>,
+) {
+    if !level_state.active {
+        for bar_id in bar_query.iter() {
+            commands.entity(bar_id).despawn_recursive();
+        }
+    }
+}
+
+fn show_progress_bar(settings: Res<Settings>, mut bar_query: Query<&mut Visibility, With<ProgressBarMarker>>) {
+    for mut visibility in bar_query.iter_mut() {
+        if visibility.is_visible != settings.show_progress_bar {
+            visibility.is_visible = settings.show_progress_bar;
+        }
+    }
+}
+
+fn update_progress(
+    player_query: Query<&Transform, With<PlayerMarker>>,
+    mut fill_query: Query<&mut Style, With<ProgressBarFillMarker>>,
+    mut text_query: Query<&mut Text, With<ProgressTextMarker>>,
+    level_state: Res<LevelState>,
+    profile: Res<Profile>,
+    mut progress: ResMut<CurrentProgress>,
+) {
+    if !level_state.active || level_state.completed {
+        return;
+    }
+
+    let length = level_state.end_x - STARTING_PLAYER_POSTION.x;
+    for transform in player_query.iter() {
+        let distance = transform.translation.x - STARTING_PLAYER_POSTION.x;
+        progress.percent = (distance / length * 100.0).clamp(0.0, 100.0);
+    }
+    progress.practice = level_state.practice;
+    progress.cheated = level_state.cheated;
+
+    for mut style in fill_query.iter_mut() {
+        style.size.width = Val::Percent(progress.percent);
+    }
+    let stats = profile.stats(&level_state.level_key);
+    let best = if level_state.practice { stats.best_practice } else { stats.best_normal };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("{}% (best {}%)", progress.percent as u32, best as u32);
+    }
+}
+
+//practice runs are kept apart from the normal best
+fn record_best_progress(
+    mut respawn_player_ev: EventReader<RespawnPlayerEvent>,
+    mut level_completed_ev: EventReader<LevelCompletedEvent>,
+    mut progress: ResMut<CurrentProgress>,
+    mut profile: ResMut<Profile>,
+    level_state: Res<LevelState>,
+) {
+    let mut percent = None;
+    if respawn_player_ev.iter().last().is_some() {
+        percent = Some(progress.percent);
+    }
+    if level_completed_ev.iter().last().is_some() {
+        percent = Some(100.0);
+    }
+
+    if let Some(percent) = percent {
+        let practice = progress.practice || level_state.practice;
+        //runs with console cheats don't count
+        if !progress.cheated && !level_state.cheated {
+            profile.record_percent(&level_state.level_key, percent, practice);
+        }
+        progress.percent = 0.0;
+    }
+}
+
+pub struct ProgressPlugin;
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentProgress>()
+            .add_system(spawn_progress_bar)
+            .add_system(despawn_progress_bar)
+            .add_system(show_progress_bar)
+            .add_system(record_best_progress)
+            .add_system(update_progress.after(record_best_progress));
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::coin::Coin;
+use crate::level::Activated;
+use crate::player::{LevelState, PlayerMarker, RespawnPlayerEvent};
+use crate::profile::Profile;
+use crate::sound::SoundEvent;
+use crate::{GameAssets, GameState, GameStateVariant};
+
+#[derive(Component)]
+pub struct ResultsScreenMarker;
+
+#[derive(Component)]
+enum ResultsButton {
+    Replay,
+    Editor,
+    Menu,
+}
+
+#[derive(Default)]
+pub struct LevelCompletedEvent;
+
+fn track_attempt_time(time: Res<Time>, mut level_state: ResMut<LevelState>) {
+    if level_state.active && !level_state.completed {
+        level_state.attempt_time += time.delta_seconds();
+        level_state.level_time += time.delta_seconds();
+    }
+}
+
+fn check_level_end(
+    player_query: Query<&Transform, With<PlayerMarker>>,
+    mut level_state: ResMut<LevelState>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut level_completed_ev: EventWriter<LevelCompletedEvent>,
+    mut sound_ev: EventWriter<SoundEvent>,
+) {
+    if !level_state.active || level_state.completed {
+        return;
+    }
+
+    for transform in player_query.iter() {
+        if transform.translation.x >= level_state.end_x {
+            level_state.completed = true;
+            rapier_config.physics_pipeline_active = false;
+            level_completed_ev.send_default();
+            sound_ev.send(SoundEvent::LevelComplete);
+            return;
+        }
+    }
+}
+
+fn spawn_results_screen(
+    mut commands: Commands,
+    mut level_completed_ev: EventReader<LevelCompletedEvent>,
+    coin_query: Query<&Activated, With<Coin>>,
+    level_state: Res<LevelState>,
+    profile: Res<Profile>,
+    game_assets: Res<GameAssets>,
+) {
+    if level_completed_ev.iter().last().is_none() {
+        return;
+    }
+
+    let stats = profile.stats(&level_state.level_key);
+    //the record may be saved after this runs, so the current run is taken into account here
+    let best_time = match stats.best_time {
+        Some(best) if level_state.practice || best < level_state.attempt_time => best,
+        _ if level_state.practice => 0.0,
+        _ => level_state.attempt_time,
+    };
+
+    let text_style = TextStyle {
+        font: game_assets.font_roboto_black.clone(),
+        font_size: 33.5,
+        color: Color::WHITE,
+    };
+
+    let lines = [
+        "Level complete!".to_string(),
+        format!("Attempts: {}", level_state.attempts),
+        format!("Time: {:.2}s", level_state.attempt_time),
+        format!("Jumps: {}", level_state.jumps),
+        format!("Total attempts: {}, total jumps: {}", stats.attempts, stats.jumps),
+        if best_time > 0.0 {
+            format!("Best time: {:.2}s", best_time)
+        } else {
+            "Best time: -".to_string()
+        },
+        format!(
+            "Coins: {}/{}",
+            coin_query.iter().filter(|activated| activated.0).count(),
+            coin_query.iter().count()
+        ),
+    ];
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..default()
+        })
+        .insert(ResultsScreenMarker)
+        .insert(Name::new("ResultsScreen"))
+        .with_children(|commands| {
+            for line in lines {
+                commands.spawn(TextBundle::from_section(line, text_style.clone()));
+            }
+
+            for (button, label) in [
+                (ResultsButton::Replay, "Replay"),
+                (ResultsButton::Editor, "Editor"),
+                (ResultsButton::Menu, "Menu"),
+            ] {
+                commands
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(200.0), Val::Px(60.0)),
+                            margin: UiRect::all(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::DARK_GRAY.into(),
+                        ..default()
+                    })
+                    .insert(button)
+                    .insert(Name::new(label))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(label, text_style.clone()));
+                    });
+            }
+        });
+}
+
+fn results_button_clicked(
+    mut commands: Commands,
+    interaction: Query<(&Interaction, &ResultsButton), Changed<Interaction>>,
+    screen_query: Query<Entity, With<ResultsScreenMarker>>,
+    mut level_state: ResMut<LevelState>,
+    mut game_state: ResMut<GameState>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut respawn_player_ev: EventWriter<RespawnPlayerEvent>,
+) {
+    for (interaction, button) in &interaction {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+
+        for screen_id in screen_query.iter() {
+            commands.entity(screen_id).despawn_recursive();
+        }
+        level_state.completed = false;
+        rapier_config.physics_pipeline_active = true;
+
+        match button {
+            ResultsButton::Replay => respawn_player_ev.send_default(),
+            ResultsButton::Editor => game_state.variant = GameStateVariant::Editor,
+            ResultsButton::Menu => game_state.variant = GameStateVariant::Menu,
+        }
+    }
+}
+
+pub struct ResultsPlugin;
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelCompletedEvent>()
+            .add_system(track_attempt_time)
+            .add_system(check_level_end)
+            .add_system(spawn_results_screen.after(check_level_end))
+            .add_system(results_button_clicked);
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use bevy::prelude::*;
+use bevy::window::{PresentMode, WindowMode};
+use bevy_egui::{egui, EguiContext};
+
+use crate::input::{first_just_pressed, Binding, InputAction, InputBindings, InputBuffer, DEFAULT_BUFFER_WINDOW};
+use crate::profile::write_atomically;
+use crate::{HEIGHT, WIDTH};
+
+const SETTINGS_DIRECTORY: &str = "geometry_dash";
+const SETTINGS_FILE: &str = "settings.json";
+const WINDOW_TITLE: &str = "Geometry Dash";
+
+const RESOLUTIONS: [(f32, f32); 5] = [
+    (1280.0, 720.0),
+    (1366.0, 768.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+    (2560.0, 1440.0),
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PracticeMusic {
+    #[default]
+    Checkpoint, //the level's song picks up at the checkpoint
+    Off,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub fullscreen: bool,
+    pub resolution: (f32, f32), //size of the window when it isn't fullscreen
+    pub vsync: bool,
+    pub show_fps: bool,
+    pub show_hitboxes: bool,
+    pub show_progress_bar: bool,
+    pub practice_music: PracticeMusic,
+    pub bindings: InputBindings,
+    pub buffer_window: f32, //seconds a press is kept for, see InputBuffer
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            fullscreen: false,
+            resolution: (WIDTH, HEIGHT),
+            vsync: true,
+            show_fps: false,
+            show_hitboxes: false,
+            show_progress_bar: true,
+            practice_music: PracticeMusic::default(),
+            bindings: InputBindings::default(),
+            buffer_window: DEFAULT_BUFFER_WINDOW,
+        }
+    }
+}
+
+impl Settings {
+    fn window_mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            width: self.resolution.0,
+            height: self.resolution.1,
+            title: WINDOW_TITLE.to_string(),
+            resizable: true,
+            mode: self.window_mode(),
+            present_mode: self.present_mode(),
+            ..default()
+        }
+    }
+}
+
+//sent by the menus that have a settings button
+#[derive(Default)]
+pub struct OpenSettingsEvent;
+
+//sent after changing the settings outside the settings window, so they get saved too
+#[derive(Default)]
+pub struct SettingsChangedEvent;
+
+#[derive(Resource, Default)]
+pub struct SettingsWindow {
+    open: bool,
+    pub listening: Option<InputAction>, //the next button pressed gets bound to this action
+    unsaved: bool,
+}
+
+fn settings_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(SETTINGS_DIRECTORY)
+        .join(SETTINGS_FILE)
+}
+
+//settings that can't be read fall back to the defaults
+pub fn load_settings() -> Settings {
+    let mut settings: Settings = File::open(settings_path())
+        .ok()
+        .and_then(|file| match serde_json::from_reader(BufReader::new(file)) {
+            Ok(settings) => Some(settings),
+            Err(error) => {
+                println!("Couldn't read the settings, using the defaults: {}", error);
+                None
+            }
+        })
+        .unwrap_or_default();
+    settings.bindings.fill_missing();
+    settings.buffer_window = settings.buffer_window.max(0.0);
+    settings
+}
+
+fn save_settings(settings: &Settings) {
+    let result = serde_json::to_vec_pretty(settings)
+        .map_err(std::io::Error::from)
+        .and_then(|contents| write_atomically(&settings_path(), &contents));
+
+    if let Err(error) = result {
+        println!("Couldn't save the settings: {}", error);
+    }
+}
+
+fn open_settings(mut open_settings_ev: EventReader<OpenSettingsEvent>, mut settings_window: ResMut<SettingsWindow>) {
+    if open_settings_ev.iter().last().is_some() {
+        settings_window.open = true;
+    }
+}
+
+//waits for the button to bind, clicks on the window itself don't count
+fn capture_binding(
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut egui_context: ResMut<EguiContext>,
+    mut settings_window: ResMut<SettingsWindow>,
+    mut settings: ResMut<Settings>,
+) {
+    let action = match settings_window.listening {
+        Some(action) => action,
+        None => return,
+    };
+
+    let binding = match first_just_pressed(&keys, &mouse_buttons, &gamepad_buttons) {
+        Some(Binding::Mouse(_)) if egui_context.ctx_mut().is_pointer_over_area() => return,
+        Some(binding) => binding,
+        None => return,
+    };
+
+    settings.bindings.bind(action, binding);
+    settings_window.listening = None;
+    settings_window.unsaved = true;
+}
+
+fn resolution_name(resolution: (f32, f32)) -> String {
+    format!("{}x{}", resolution.0, resolution.1)
+}
+
+//edits a copy, so the settings only count as changed when something was actually changed
+fn settings_panel(
+    mut egui_context: ResMut<EguiContext>,
+    mut settings_window: ResMut<SettingsWindow>,
+    mut settings: ResMut<Settings>,
+) {
+    if !settings_window.open {
+        return;
+    }
+
+    let mut edited = settings.clone();
+    let mut open = true;
+    let mut listening = settings_window.listening;
+    egui::Window::new("Settings").open(&mut open).show(egui_context.ctx_mut(), |ui| {
+        ui.heading("Audio");
+        ui.add(egui::Slider::new(&mut edited.music_volume, 0.0..=1.0).text("Music"));
+        ui.add(egui::Slider::new(&mut edited.sfx_volume, 0.0..=1.0).text("Sound effects"));
+
+        ui.heading("Video");
+        ui.checkbox(&mut edited.fullscreen, "Fullscreen");
+        egui::ComboBox::from_label("Resolution")
+            .selected_text(resolution_name(edited.resolution))
+            .show_ui(ui, |ui| {
+                for resolution in RESOLUTIONS {
+                    ui.selectable_value(&mut edited.resolution, resolution, resolution_name(resolution));
+                }
+            });
+        ui.checkbox(&mut edited.vsync, "Vsync");
+        ui.checkbox(&mut edited.show_fps, "Show FPS");
+        ui.checkbox(&mut edited.show_hitboxes, "Show hitboxes");
+        ui.checkbox(&mut edited.show_progress_bar, "Show progress bar");
+
+        ui.heading("Gameplay");
+        egui::ComboBox::from_label("Music in practice")
+            .selected_text(format!("{:?}", edited.practice_music))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut edited.practice_music, PracticeMusic::Checkpoint, "Checkpoint");
+                ui.selectable_value(&mut edited.practice_music, PracticeMusic::Off, "Off");
+            });
+        //how early a press before landing or reaching an orb still counts
+        let mut window_ms = edited.buffer_window * 1000.0;
+        if ui
+            .add(egui::Slider::new(&mut window_ms, 0.0..=250.0).suffix(" ms").text("Input buffer"))
+            .changed()
+        {
+            edited.buffer_window = window_ms / 1000.0;
+        }
+
+        ui.heading("Controls");
+        ui.label("Click a binding to remove it");
+        egui::Grid::new("bindings").striped(true).show(ui, |ui| {
+            for action in InputAction::ALL {
+                ui.label(action.name());
+                ui.horizontal(|ui| {
+                    for binding in edited.bindings.bindings(action).to_vec() {
+                        if ui.button(binding.name()).clicked() {
+                            edited.bindings.unbind(action, binding);
+                        }
+                    }
+
+                    if listening == Some(action) {
+                        if ui.button("Press a button... (cancel)").clicked() {
+                            listening = None;
+                        }
+                    } else if ui.button("+").clicked() {
+                        listening = Some(action);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Reset controls").clicked() {
+                edited.bindings = InputBindings::default();
+            }
+            if ui.button("Reset everything").clicked() {
+                edited = Settings::default();
+            }
+        });
+    });
+
+    settings_window.listening = if open { listening } else { None };
+    settings_window.open = open;
+    if edited != *settings {
+        *settings = edited;
+        settings_window.unsaved = true;
+    }
+}
+
+//waits until sliders are let go of, so dragging doesn't write the file every frame
+fn save_changed_settings(
+    mut egui_context: ResMut<EguiContext>,
+    mut settings_window: ResMut<SettingsWindow>,
+    mut settings_changed_ev: EventReader<SettingsChangedEvent>,
+    settings: Res<Settings>,
+) {
+    if settings_changed_ev.iter().last().is_some() {
+        settings_window.unsaved = true;
+    }
+    if settings_window.unsaved && !egui_context.ctx_mut().input().pointer.any_down() {
+        save_settings(&settings);
+        settings_window.unsaved = false;
+    }
+}
+
+//only when the window settings themselves changed, otherwise a resized window would snap back
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut windows: ResMut<Windows>,
+    mut applied: Local<Option<((f32, f32), bool, bool)>>,
+) {
+    let window_settings = (settings.resolution, settings.fullscreen, settings.vsync);
+    //the window was created with these
+    let previous = match applied.replace(window_settings) {
+        Some(previous) => previous,
+        None => return,
+    };
+    if previous == window_settings {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_mode(settings.window_mode());
+        window.set_present_mode(settings.present_mode());
+        if !settings.fullscreen {
+            window.set_resolution(settings.resolution.0, settings.resolution.1);
+        }
+    }
+}
+
+fn apply_input_settings(settings: Res<Settings>, mut input_buffer: ResMut<InputBuffer>) {
+    if settings.is_changed() {
+        input_buffer.window = settings.buffer_window;
+    }
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputBuffer::new(DEFAULT_BUFFER_WINDOW))
+            .init_resource::<SettingsWindow>()
+            .add_event::<OpenSettingsEvent>()
+            .add_event::<SettingsChangedEvent>()
+            .add_system(open_settings)
+            .add_system(capture_binding.before(settings_panel))
+            .add_system(settings_panel)
+            .add_system(save_changed_settings.after(settings_panel))
+            .add_system(apply_window_settings)
+            .add_system(apply_input_settings);
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::level::ASSETS_DIRECTORY;
+use crate::settings::Settings;
+
+//systems send these instead of playing audio themselves
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SoundEvent {
+    Death,
+    Jump,
+    Coin,
+    Orb,
+    LevelComplete,
+    EditorPlace,
+    EditorDelete,
+}
+
+impl SoundEvent {
+    const ALL: [SoundEvent; 7] = [
+        SoundEvent::Death,
+        SoundEvent::Jump,
+        SoundEvent::Coin,
+        SoundEvent::Orb,
+        SoundEvent::LevelComplete,
+        SoundEvent::EditorPlace,
+        SoundEvent::EditorDelete,
+    ];
+
+    fn path(&self) -> &'static str {
+        match self {
+            SoundEvent::Death => "sounds/death.ogg",
+            SoundEvent::Jump => "sounds/jump.ogg",
+            SoundEvent::Coin => "sounds/coin.ogg",
+            SoundEvent::Orb => "sounds/orb.ogg",
+            SoundEvent::LevelComplete => "sounds/complete.ogg",
+            SoundEvent::EditorPlace => "sounds/place.ogg",
+            SoundEvent::EditorDelete => "sounds/delete.ogg",
+        }
+    }
+
+    //how loud the sound is compared to the others
+    fn volume(&self) -> f32 {
+        match self {
+            SoundEvent::Jump | SoundEvent::EditorPlace | SoundEvent::EditorDelete => 0.5,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SoundSettings {
+    pub muted: bool, //headless and test runs have no one listening
+}
+
+#[derive(Resource)]
+struct SoundAssets {
+    sounds: Vec<(SoundEvent, Handle<AudioSource>)>,
+}
+
+//sounds without a file are left out, so they stay silent instead of failing to load every time
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let (found, missing): (Vec<SoundEvent>, Vec<SoundEvent>) = SoundEvent::ALL
+        .iter()
+        .copied()
+        .partition(|sound| Path::new(ASSETS_DIRECTORY).join(sound.path()).is_file());
+    if !missing.is_empty() {
+        let paths: Vec<&str> = missing.iter().map(|sound| sound.path()).collect();
+        warn!("Sound files not found in {}, these stay silent: {}", ASSETS_DIRECTORY, paths.join(", "));
+    }
+
+    commands.insert_resource(SoundAssets {
+        sounds: found
+            .into_iter()
+            .map(|sound| (sound, asset_server.load(sound.path())))
+            .collect(),
+    });
+}
+
+//audio is optional so the game also runs without the audio plugin
+fn play_sounds(
+    mut sound_ev: EventReader<SoundEvent>,
+    audio: Option<Res<Audio>>,
+    sound_assets: Res<SoundAssets>,
+    sound_settings: Res<SoundSettings>,
+    settings: Res<Settings>,
+) {
+    let audio = match audio {
+        Some(audio) if !sound_settings.muted => audio,
+        _ => {
+            sound_ev.clear();
+            return;
+        }
+    };
+
+    for sound in sound_ev.iter() {
+        if let Some((_, handle)) = sound_assets.sounds.iter().find(|(event, _)| event == sound) {
+            audio.play_with_settings(
+                handle.clone(),
+                PlaybackSettings::ONCE.with_volume(settings.sfx_volume * sound.volume()),
+            );
+        }
+    }
+}
+
+pub struct SoundPlugin;
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SoundEvent>()
+            .add_startup_system(load_sounds)
+            .add_system(play_sounds);
+    }
+}
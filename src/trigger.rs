@@ -0,0 +1,357 @@
+use serde::{Deserialize, Serialize};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::color::ColorChannels;
+use crate::level::Activated;
+use crate::player::{
+    reset_player_state, CameraFraming, DualPlayerMarker, LevelCameraMarker, LevelState, PlayerMarker,
+    RespawnPlayerEvent, Speed,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 4] = [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut];
+
+    //maps linear progress (0..1) onto the curve
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum TriggerKind {
+    Move { offset: (f32, f32) },
+    Rotate { degrees: f32, center_group: u16 }, //counter-clockwise around the middle of the center group, 0 spins every object around itself
+    Alpha { opacity: f32 },
+    Toggle { enabled: bool }, //instant, the duration is ignored
+    Color { channel: u16, color: [f32; 3] }, //blends a color channel, the target group is ignored
+    //camera triggers ignore the target group too
+    CameraOffset { offset: (f32, f32) },
+    CameraZoom { zoom: f32 },
+    CameraStatic { position: Option<(f32, f32)> }, //instant, None lets the camera follow the player again
+    CameraSection { bounds: Option<(f32, f32)> },  //instant, frames everything between a floor and a ceiling
+}
+
+impl TriggerKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerKind::Move { .. } => "Move",
+            TriggerKind::Rotate { .. } => "Rotate",
+            TriggerKind::Alpha { .. } => "Alpha",
+            TriggerKind::Toggle { .. } => "Toggle",
+            TriggerKind::Color { .. } => "Color",
+            TriggerKind::CameraOffset { .. } => "CameraOffset",
+            TriggerKind::CameraZoom { .. } => "CameraZoom",
+            TriggerKind::CameraStatic { .. } => "CameraStatic",
+            TriggerKind::CameraSection { .. } => "CameraSection",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TriggerInfo {
+    pub target_group: u16,
+    pub duration: f32,
+    #[serde(default)]
+    pub easing: Easing,
+    pub kind: TriggerKind,
+}
+
+impl TriggerInfo {
+    pub fn new(kind: TriggerKind) -> Self {
+        TriggerInfo {
+            target_group: 1,
+            duration: 0.5,
+            easing: Easing::Linear,
+            kind,
+        }
+    }
+
+    pub fn default_move() -> Self {
+        TriggerInfo::new(TriggerKind::Move { offset: (0.0, 64.0) })
+    }
+}
+
+#[derive(Component)]
+pub struct Trigger {
+    pub info: TriggerInfo,
+}
+
+//what a group member looked like when the level was loaded
+struct InitialState {
+    transform: Transform,
+    alpha: f32,
+    visible: bool,
+    collider: Option<Collider>,
+}
+
+//objects that triggers can animate, initial is filled in once the object exists
+#[derive(Component)]
+pub struct GroupMember {
+    pub groups: Vec<u16>,
+    initial: Option<InitialState>,
+    enabled: bool,
+}
+
+impl GroupMember {
+    pub fn new(groups: Vec<u16>) -> Self {
+        GroupMember {
+            groups,
+            initial: None,
+            enabled: true,
+        }
+    }
+}
+
+struct RunningTrigger {
+    info: TriggerInfo,
+    elapsed: f32,
+    applied: f32, //eased progress that was already applied to the group
+}
+
+#[derive(Resource, Default)]
+struct RunningTriggers {
+    list: Vec<RunningTrigger>,
+}
+
+fn capture_group_initial_state(
+    mut member_query: Query<
+        (&Transform, &Visibility, Option<&Sprite>, Option<&Collider>, &mut GroupMember),
+        Added<GroupMember>,
+    >,
+) {
+    for (transform, visibility, sprite, collider, mut member) in member_query.iter_mut() {
+        member.initial = Some(InitialState {
+            transform: *transform,
+            alpha: sprite.map(|sprite| sprite.color.a()).unwrap_or(1.0),
+            visible: visibility.is_visible,
+            collider: collider.cloned(),
+        });
+    }
+}
+
+//hides the object and takes its collider away, the initial state keeps a copy to bring it back
+fn set_member_enabled(
+    commands: &mut Commands,
+    entity: Entity,
+    member: &mut GroupMember,
+    visibility: &mut Visibility,
+    enabled: bool,
+) {
+    if member.enabled == enabled {
+        return;
+    }
+    member.enabled = enabled;
+    visibility.is_visible = enabled;
+
+    if let Some(collider) = member.initial.as_ref().and_then(|initial| initial.collider.clone()) {
+        if enabled {
+            commands.entity(entity).insert(collider);
+        } else {
+            commands.entity(entity).remove::<Collider>();
+        }
+    }
+}
+
+//triggers are invisible while playing
+fn hide_triggers(mut trigger_query: Query<&mut Visibility, Added<Trigger>>, level_state: Res<LevelState>) {
+    if level_state.active {
+        for mut visibility in trigger_query.iter_mut() {
+            visibility.is_visible = false;
+        }
+    }
+}
+
+fn activate_triggers(
+    player_query: Query<(&Transform, &Speed), (With<PlayerMarker>, Without<DualPlayerMarker>)>,
+    mut trigger_query: Query<(&Transform, &Trigger, &mut Activated), Without<PlayerMarker>>,
+    level_state: Res<LevelState>,
+    mut running: ResMut<RunningTriggers>,
+) {
+    if !level_state.active || level_state.completed || level_state.death_timer.is_some() {
+        return;
+    }
+
+    for (player_transform, speed) in player_query.iter() {
+        for (transform, trigger, mut activated) in trigger_query.iter_mut() {
+            if !activated.0 && player_transform.translation.x >= transform.translation.x {
+                activated.0 = true;
+                //a player spawned past the trigger (e.g. at a checkpoint) catches up right away
+                let elapsed = (player_transform.translation.x - transform.translation.x) / speed.tier.value();
+                running.list.push(RunningTrigger {
+                    info: trigger.info.clone(),
+                    elapsed,
+                    applied: 0.0,
+                });
+            }
+        }
+    }
+}
+
+fn run_triggers(
+    mut commands: Commands,
+    mut member_query: Query<(Entity, &mut Transform, &mut GroupMember, &mut Visibility, Option<&mut Sprite>)>,
+    time: Res<Time>,
+    level_state: Res<LevelState>,
+    mut running: ResMut<RunningTriggers>,
+    mut color_channels: ResMut<ColorChannels>,
+    mut camera_query: Query<&mut CameraFraming, With<LevelCameraMarker>>,
+) {
+    if !level_state.active || level_state.completed {
+        return;
+    }
+
+    for trigger in running.list.iter_mut() {
+        trigger.elapsed += time.delta_seconds();
+        let instant = matches!(
+            trigger.info.kind,
+            TriggerKind::Toggle { .. } | TriggerKind::CameraStatic { .. } | TriggerKind::CameraSection { .. }
+        );
+        let t = if instant || trigger.info.duration <= 0.0 {
+            1.0
+        } else {
+            (trigger.elapsed / trigger.info.duration).min(1.0)
+        };
+        let progress = trigger.info.easing.apply(t);
+        let step = progress - trigger.applied;
+        //share of the remaining way to a target value that this frame covers
+        let remaining_share = if trigger.applied < 1.0 { step / (1.0 - trigger.applied) } else { 1.0 };
+        trigger.applied = progress;
+
+        match trigger.info.kind {
+            TriggerKind::Color { channel, color } => {
+                color_channels.blend(channel, color, remaining_share);
+                continue;
+            }
+            TriggerKind::CameraOffset { offset } => {
+                for mut framing in camera_query.iter_mut() {
+                    let offset = Vec2::new(offset.0, offset.1);
+                    framing.offset = framing.offset + (offset - framing.offset) * remaining_share;
+                }
+                continue;
+            }
+            TriggerKind::CameraZoom { zoom } => {
+                for mut framing in camera_query.iter_mut() {
+                    framing.trigger_zoom += (zoom.max(0.1) - framing.trigger_zoom) * remaining_share;
+                }
+                continue;
+            }
+            TriggerKind::CameraStatic { position } => {
+                for mut framing in camera_query.iter_mut() {
+                    framing.static_position = position.map(|position| Vec2::new(position.0, position.1));
+                }
+                continue;
+            }
+            TriggerKind::CameraSection { bounds } => {
+                for mut framing in camera_query.iter_mut() {
+                    framing.section = bounds;
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        //the center is looked up every frame since another trigger could be moving it,
+        //with several objects in the center group their middle is used
+        let center = match trigger.info.kind {
+            TriggerKind::Rotate { center_group, .. } if center_group != 0 => {
+                let (sum, count) = member_query
+                    .iter()
+                    .filter(|(_, _, member, _, _)| member.groups.contains(&center_group))
+                    .fold((Vec3::ZERO, 0), |(sum, count), (_, transform, _, _, _)| {
+                        (sum + transform.translation, count + 1)
+                    });
+                (count > 0).then(|| sum / count as f32)
+            }
+            _ => None,
+        };
+
+        for (entity, mut transform, mut member, mut visibility, sprite) in member_query.iter_mut() {
+            if !member.groups.contains(&trigger.info.target_group) {
+                continue;
+            }
+
+            match trigger.info.kind {
+                TriggerKind::Move { offset } => {
+                    transform.translation += Vec3::new(offset.0, offset.1, 0.0) * step;
+                }
+                TriggerKind::Rotate { degrees, .. } => {
+                    let rotation = Quat::from_rotation_z((degrees * step).to_radians());
+                    if let Some(center) = center {
+                        transform.rotate_around(center, rotation);
+                    } else {
+                        transform.rotate(rotation);
+                    }
+                }
+                TriggerKind::Alpha { opacity } => {
+                    if let Some(mut sprite) = sprite {
+                        let alpha = sprite.color.a();
+                        sprite.color.set_a(alpha + (opacity.clamp(0.0, 1.0) - alpha) * remaining_share);
+                    }
+                }
+                TriggerKind::Toggle { enabled } => {
+                    set_member_enabled(&mut commands, entity, &mut member, &mut visibility, enabled);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    running.list.retain(|trigger| trigger.applied < 1.0);
+}
+
+fn reset_triggers(
+    mut commands: Commands,
+    mut respawn_player_ev: EventReader<RespawnPlayerEvent>,
+    mut member_query: Query<(Entity, &mut Transform, &mut GroupMember, &mut Visibility, Option<&mut Sprite>)>,
+    mut running: ResMut<RunningTriggers>,
+) {
+    if respawn_player_ev.iter().last().is_none() {
+        return;
+    }
+
+    running.list.clear();
+    for (entity, mut transform, mut member, mut visibility, sprite) in member_query.iter_mut() {
+        set_member_enabled(&mut commands, entity, &mut member, &mut visibility, true);
+        if let Some(initial) = member.initial.as_ref() {
+            *transform = initial.transform;
+            visibility.is_visible = initial.visible;
+            if let Some(mut sprite) = sprite {
+                sprite.color.set_a(initial.alpha);
+            }
+        }
+    }
+}
+
+pub struct TriggerPlugin;
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunningTriggers>()
+            .add_system(capture_group_initial_state.after(hide_triggers))
+            .add_system(hide_triggers)
+            .add_system(activate_triggers)
+            .add_system(run_triggers.after(activate_triggers))
+            .add_system(reset_triggers.after(reset_player_state).before(activate_triggers));
+    }
+}